@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{angle_delta, angles_to, viewangle_delta};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::ParserState;
+
+// Looks at every engagement (a stretch where the same enemy stays closest to a player's crosshair)
+// for two aimbot patterns:
+// - Target lock: the crosshair stays on the enemy's head with near-zero error for many updates,
+//   even while the enemy changes strafing direction, which a human can only follow with a delay.
+// - Smooth aimbot: the crosshair moves onto the target at a constant rate, or closes a constant
+//   fraction of the remaining error every update. Human flicks speed up and slow down instead.
+// Each flagged engagement is reported once, with the tracking error statistics as evidence.
+
+// Changes in the enemy's bearing smaller than this (degrees per update) don't count as strafing
+const MIN_STRAFE_RATE: f32 = 0.1;
+
+struct Sample {
+    tick: u32,
+    // Degrees between the crosshair and the target's head
+    error: f32,
+    // Bearing of the target's head as seen from the player's eye (yaw, pitch)
+    bearing: (f32, f32),
+    view: (f32, f32),
+    distance: f32,
+}
+
+struct Engagement {
+    target: EntityId,
+    target_steam_id: u64,
+    samples: Vec<Sample>,
+}
+
+#[derive(Default)]
+struct AimTracker {
+    sim_tick: u32,
+    engagement: Option<Engagement>,
+}
+
+fn mean_std(values: impl Iterator<Item = f32> + Clone) -> (f32, f32) {
+    let n = values.clone().count().max(1) as f32;
+    let mean = values.clone().sum::<f32>() / n;
+    let var = values.map(|v| (v - mean).powi(2)).sum::<f32>() / n;
+    (mean, var.sqrt())
+}
+
+// Coefficient of variation
+fn variation(values: &[f32]) -> f32 {
+    let (mean, std) = mean_std(values.iter().copied());
+    if mean <= 0.0 {
+        return f32::INFINITY;
+    }
+    std / mean
+}
+
+// Number of times the target's bearing reverses its horizontal direction
+fn direction_changes(samples: &[Sample]) -> u32 {
+    let mut changes = 0;
+    let mut last_sign = 0.0;
+    for pair in samples.windows(2) {
+        let (rate, _) = viewangle_delta(pair[1].bearing.0, 0.0, pair[0].bearing.0, 0.0, 1);
+        if rate.abs() < MIN_STRAFE_RATE {
+            continue;
+        }
+        let sign = rate.signum();
+        if last_sign != 0.0 && sign != last_sign {
+            changes += 1;
+        }
+        last_sign = sign;
+    }
+    changes
+}
+
+#[derive(Default)]
+pub struct AimLock {
+    params: Parameters,
+
+    trackers: HashMap<u64, AimTracker>,
+}
+
+impl AimLock {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                // An enemy has to be this close to the crosshair to start an engagement
+                ("engagement_fov".to_string(), Parameter::Float(20.0)),
+                // The head position is an estimate, so allow a small constant offset
+                ("max_lock_angle".to_string(), Parameter::Float(1.0)),
+                ("min_lock_ticks".to_string(), Parameter::Int(33)),
+                ("min_direction_changes".to_string(), Parameter::Int(2)),
+                ("min_smooth_ticks".to_string(), Parameter::Int(6)),
+                ("min_smooth_start_angle".to_string(), Parameter::Float(5.0)),
+                ("max_smooth_variation".to_string(), Parameter::Float(0.1)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    // Stretches of the engagement where the crosshair stayed on the head through strafes
+    fn find_locks(&self, samples: &[Sample]) -> Vec<Value> {
+        let max_lock_angle: f32 = get_parameter_value(&self.params, "max_lock_angle");
+        let min_lock_ticks: i32 = get_parameter_value(&self.params, "min_lock_ticks");
+        let min_direction_changes: i32 = get_parameter_value(&self.params, "min_direction_changes");
+
+        samples
+            .split(|s| s.error > max_lock_angle)
+            .filter_map(|run| {
+                let (first, last) = (run.first()?, run.last()?);
+                let ticks = last.tick - first.tick;
+                let changes = direction_changes(run);
+                if (ticks as i32) < min_lock_ticks || (changes as i32) < min_direction_changes {
+                    return None;
+                }
+                let (mean_error, std_error) = mean_std(run.iter().map(|s| s.error));
+                Some(json!({
+                    "start_tick": first.tick,
+                    "end_tick": last.tick,
+                    "ticks": ticks,
+                    "direction_changes": changes,
+                    "mean_error": mean_error,
+                    "std_error": std_error,
+                    "max_error": run.iter().map(|s| s.error).fold(0.0, f32::max),
+                }))
+            })
+            .collect()
+    }
+
+    // Approaches onto the target where the crosshair moved at a constant rate, or closed a constant
+    // fraction of the remaining error, every update
+    fn find_smooth_approaches(&self, samples: &[Sample]) -> Vec<Value> {
+        let max_lock_angle: f32 = get_parameter_value(&self.params, "max_lock_angle");
+        let min_smooth_ticks: i32 = get_parameter_value(&self.params, "min_smooth_ticks");
+        let min_smooth_start_angle: f32 = get_parameter_value(&self.params, "min_smooth_start_angle");
+        let max_smooth_variation: f32 = get_parameter_value(&self.params, "max_smooth_variation");
+
+        let mut approaches = Vec::new();
+        let mut start = 0;
+        for end in 1..=samples.len() {
+            if end < samples.len() && samples[end].error < samples[end - 1].error {
+                continue;
+            }
+            let run = &samples[start..end];
+            start = end;
+            if (run.len() as i32) < min_smooth_ticks {
+                continue;
+            }
+            let (first, last) = (&run[0], &run[run.len() - 1]);
+            if first.error < min_smooth_start_angle || last.error > max_lock_angle {
+                continue;
+            }
+            let steps: Vec<f32> = run.windows(2).map(|p| angle_delta(p[0].view, p[1].view)).collect();
+            let fractions: Vec<f32> = run
+                .windows(2)
+                .zip(&steps)
+                .map(|(p, step)| step / p[0].error.max(f32::EPSILON))
+                .collect();
+            let (linear, exponential) = (variation(&steps), variation(&fractions));
+            let kind = if linear <= max_smooth_variation {
+                "constant_rate"
+            } else if exponential <= max_smooth_variation {
+                "constant_fraction"
+            } else {
+                continue;
+            };
+            approaches.push(json!({
+                "start_tick": first.tick,
+                "end_tick": last.tick,
+                "type": kind,
+                "start_error": first.error,
+                "end_error": last.error,
+                "rate_variation": linear,
+                "fraction_variation": exponential,
+                "steps": steps,
+            }));
+        }
+        approaches
+    }
+
+    fn end_engagement(&self, steam_id: u64, tracker: &mut AimTracker) -> Option<Detection> {
+        let engagement = tracker.engagement.take()?;
+        let samples = &engagement.samples;
+        let (first, last) = (samples.first()?, samples.last()?);
+
+        let locks = self.find_locks(samples);
+        let smooth_approaches = self.find_smooth_approaches(samples);
+        if locks.is_empty() && smooth_approaches.is_empty() {
+            return None;
+        }
+
+        let (mean_error, std_error) = mean_std(samples.iter().map(|s| s.error));
+        Some(Detection {
+            tick: first.tick,
+            algorithm: self.algorithm_name().to_string(),
+            player: steam_id,
+            data: json!({
+                "target": engagement.target_steam_id,
+                "start_tick": first.tick,
+                "end_tick": last.tick,
+                "samples": samples.len(),
+                "mean_distance": samples.iter().map(|s| s.distance).sum::<f32>() / samples.len() as f32,
+                "mean_error": mean_error,
+                "std_error": std_error,
+                "min_error": samples.iter().map(|s| s.error).fold(f32::INFINITY, f32::min),
+                "direction_changes": direction_changes(samples),
+                "locks": locks,
+                "smooth_approaches": smooth_approaches,
+            }),
+        })
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for AimLock {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "aim_lock"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let engagement_fov: f32 = get_parameter_value(&self.params, "engagement_fov");
+        let ticknum = u32::from(state.tick);
+
+        let mut detections = Vec::new();
+        let mut trackers = std::mem::take(&mut self.trackers);
+
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            let tracked =
+                player.in_pvs && player.state == PlayerState::Alive && info.steam_id != "BOT";
+            if !tracked {
+                if let Some(tracker) = trackers.get_mut(&steam_id) {
+                    detections.extend(self.end_engagement(steam_id, tracker));
+                    tracker.sim_tick = 0;
+                }
+                continue;
+            }
+
+            let tracker = trackers.entry(steam_id).or_default();
+            if player.simulation_tick <= tracker.sim_tick {
+                continue;
+            }
+            tracker.sim_tick = player.simulation_tick;
+
+            let target = state
+                .get_aim(player)
+                .and_then(|aim| aim.closest())
+                .filter(|t| t.head_angle <= engagement_fov)
+                .copied();
+            let current = tracker.engagement.as_ref().map(|e| e.target);
+            if current.is_some() && current != target.map(|t| t.entity) {
+                detections.extend(self.end_engagement(steam_id, tracker));
+            }
+            let Some(target) = target else {
+                continue;
+            };
+            let Some(target_player) = state.get_player_by_entid(target.entity) else {
+                continue;
+            };
+
+            let engagement = tracker.engagement.get_or_insert_with(|| Engagement {
+                target: target.entity,
+                target_steam_id: target_player
+                    .info
+                    .as_ref()
+                    .and_then(|i| SteamID::from_steam3(&i.steam_id).ok())
+                    .map_or(0, u64::from),
+                samples: Vec::new(),
+            });
+            engagement.samples.push(Sample {
+                tick: ticknum,
+                error: target.head_angle,
+                bearing: angles_to(player.eye_position(), target_player.eye_position()),
+                view: (player.view_angle, player.pitch_angle),
+                distance: target.distance,
+            });
+        }
+
+        self.trackers = trackers;
+        Ok(detections)
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let mut trackers = std::mem::take(&mut self.trackers);
+        let mut detections = Vec::new();
+        for (steam_id, tracker) in trackers.iter_mut() {
+            detections.extend(self.end_engagement(*steam_id, tracker));
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use anyhow::Error;
+use serde_json::json;
+use steamid_ng::SteamID;
+use tf_demo_parser::ParserState;
+
+use crate::base::cheat_analyser_base::CheatAnalyserState;
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+
+// Writes the aim target estimates of every player to ./output/aim_telemetry.csv
+// (one row per player and enemy), or to ./output/aim_telemetry.json with the json parameter.
+#[derive(Default)]
+pub struct AimTelemetry {
+    file: Option<BufWriter<File>>,
+    // Whether a JSON record has been written yet
+    written: bool,
+    params: Parameters,
+}
+
+impl AimTelemetry {
+    pub fn new() -> Self {
+        AimTelemetry {
+            params: HashMap::from([("json".to_string(), Parameter::Bool(false))]),
+            ..Default::default()
+        }
+    }
+
+    fn init_file(&mut self, file_path: &str) {
+        self.file = Some(BufWriter::new(match File::create(file_path) {
+            Ok(file) => file,
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::AlreadyExists {
+                    panic!("Error creating file: {}", err);
+                }
+                fs::remove_file(file_path).unwrap();
+                File::create(file_path).unwrap()
+            }
+        }));
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for AimTelemetry {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "aim_telemetry"
+    }
+
+    fn init(&mut self) -> Result<(), Error> {
+        let json: bool = get_parameter_value(&self.params, "json");
+        if json {
+            self.init_file("./output/aim_telemetry.json");
+            writeln!(self.file.as_mut().unwrap(), "[")?;
+        } else {
+            self.init_file("./output/aim_telemetry.csv");
+            writeln!(
+                self.file.as_mut().unwrap(),
+                "tick,steam_id,target_steam_id,head_angle,body_angle,distance,closest"
+            )?;
+        }
+        Ok(())
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let json: bool = get_parameter_value(&self.params, "json");
+        let ticknum = u32::from(state.tick);
+
+        let steam_ids: HashMap<_, _> = state
+            .players
+            .iter()
+            .filter_map(|p| {
+                let info = p.info.as_ref()?;
+                let steam_id = SteamID::from_steam3(&info.steam_id).map_or(0, u64::from);
+                Some((p.entity_id(), steam_id))
+            })
+            .collect();
+
+        for (entity, estimate) in &state.aim {
+            let Some(steam_id) = steam_ids.get(entity) else {
+                continue;
+            };
+            let closest = estimate.closest().map(|t| t.entity);
+            let file = self.file.as_mut().unwrap();
+            if json {
+                if std::mem::replace(&mut self.written, true) {
+                    writeln!(file, ",")?;
+                }
+                let targets: Vec<_> = estimate
+                    .targets
+                    .iter()
+                    .map(|t| {
+                        json!({
+                            "steam_id": steam_ids.get(&t.entity),
+                            "head_angle": t.head_angle,
+                            "body_angle": t.body_angle,
+                            "distance": t.distance,
+                        })
+                    })
+                    .collect();
+                write!(
+                    file,
+                    "{}",
+                    json!({
+                        "tick": ticknum,
+                        "steam_id": steam_id,
+                        "closest": closest.and_then(|c| steam_ids.get(&c)),
+                        "targets": targets,
+                    })
+                )?;
+            } else {
+                for target in &estimate.targets {
+                    writeln!(
+                        file,
+                        "{},{},{},{},{},{},{}",
+                        ticknum,
+                        steam_id,
+                        steam_ids.get(&target.entity).copied().unwrap_or_default(),
+                        target.head_angle,
+                        target.body_angle,
+                        target.distance,
+                        closest == Some(target.entity)
+                    )?;
+                }
+            }
+        }
+
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let json: bool = get_parameter_value(&self.params, "json");
+        if let Some(file) = self.file.as_mut() {
+            if json {
+                writeln!(file, "\n]")?;
+            }
+            file.flush()?;
+        }
+        Ok(vec![])
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
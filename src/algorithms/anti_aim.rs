@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, Player, PlayerCondition, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::viewangle_delta;
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::ParserState;
+
+// Finds sustained anti-aim in each player's view angles, one window of updates at a time:
+// - Spinbot: the yaw keeps rotating in the same direction faster than anyone can turn a mouse.
+// - Jitter: the yaw flips back and forth between fixed offsets with a short, regular period. The
+//   period is the strongest frequency of the (detrended) yaw series, and only counts if the series
+//   also correlates with itself shifted by that period.
+// - Fake pitch: the pitch keeps switching between looking straight up and straight down.
+// Consecutive windows with the same pattern are reported as one detection.
+
+const TICK_INTERVAL: f32 = 0.015;
+
+// Update gaps larger than this start a new window
+const MAX_SAMPLE_GAP: u32 = 8;
+// Pitch at least this far up or down counts as an extreme for fake pitch
+const EXTREME_PITCH: f32 = 80.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Pattern {
+    Spinbot,
+    Jitter,
+    FakePitch,
+}
+
+// (tick, simulation tick, yaw, pitch)
+type Sample = (u32, u32, f32, f32);
+
+struct Ongoing {
+    pattern: Pattern,
+    start_tick: u32,
+    end_tick: u32,
+    windows: Vec<Value>,
+}
+
+#[derive(Default)]
+struct AntiAimTracker {
+    samples: Vec<Sample>,
+    ongoing: Option<Ongoing>,
+}
+
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len().max(1) as f32
+}
+
+// Yaw series without wrapping around at +-180
+fn unwrap_yaw(samples: &[Sample]) -> Vec<f32> {
+    let mut yaw = samples[0].2;
+    let mut unwrapped = vec![yaw];
+    for pair in samples.windows(2) {
+        yaw += viewangle_delta(pair[1].2, 0.0, pair[0].2, 0.0, 1).0;
+        unwrapped.push(yaw);
+    }
+    unwrapped
+}
+
+// Residuals of a least squares line through the series
+fn detrend(values: &[f32]) -> Vec<f32> {
+    let n = values.len() as f32;
+    let mean_t = (n - 1.0) / 2.0;
+    let mean_v = mean(values);
+    let (cov, var) = values.iter().enumerate().fold((0.0, 0.0), |(cov, var), (t, v)| {
+        let dt = t as f32 - mean_t;
+        (cov + dt * (v - mean_v), var + dt * dt)
+    });
+    let slope = if var > 0.0 { cov / var } else { 0.0 };
+    values
+        .iter()
+        .enumerate()
+        .map(|(t, v)| v - mean_v - slope * (t as f32 - mean_t))
+        .collect()
+}
+
+// Strongest frequency of the series (as a period in samples) and its share of the total power
+fn dominant_period(values: &[f32]) -> Option<(f32, f32)> {
+    let n = values.len();
+    let powers: Vec<f32> = (1..=n / 2)
+        .map(|k| {
+            let (re, im) = values.iter().enumerate().fold((0.0, 0.0), |(re, im), (t, v)| {
+                let phase = 2.0 * PI * (k * t) as f32 / n as f32;
+                (re + v * phase.cos(), im - v * phase.sin())
+            });
+            re * re + im * im
+        })
+        .collect();
+    let total: f32 = powers.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let (k, power) = powers
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    Some((n as f32 / (k + 1) as f32, power / total))
+}
+
+// Correlation of the (zero mean) series with itself shifted by `lag` samples
+fn autocorrelation(values: &[f32], lag: usize) -> f32 {
+    let var: f32 = values.iter().map(|v| v * v).sum();
+    if var <= 0.0 || lag >= values.len() {
+        return 0.0;
+    }
+    let cov: f32 = values.iter().zip(&values[lag..]).map(|(a, b)| a * b).sum();
+    cov / var * values.len() as f32 / (values.len() - lag) as f32
+}
+
+// Taunts and karts can spin the player legitimately
+fn exempt(player: &Player) -> bool {
+    player.has_condition(PlayerCondition::Taunting)
+        || player.has_condition(PlayerCondition::HalloweenKart)
+}
+
+#[derive(Default)]
+pub struct AntiAim {
+    params: Parameters,
+
+    trackers: HashMap<u64, AntiAimTracker>,
+}
+
+impl AntiAim {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("window".to_string(), Parameter::Int(66)),
+                // Degrees per second
+                ("min_spin_rate".to_string(), Parameter::Float(1440.0)),
+                // Share of the yaw movement that has to go in the spin direction
+                ("min_spin_consistency".to_string(), Parameter::Float(0.9)),
+                ("min_jitter_amplitude".to_string(), Parameter::Float(30.0)),
+                // Ticks
+                ("max_jitter_period".to_string(), Parameter::Float(8.0)),
+                ("min_jitter_power".to_string(), Parameter::Float(0.5)),
+                ("min_jitter_autocorrelation".to_string(), Parameter::Float(0.8)),
+                ("min_pitch_switches".to_string(), Parameter::Int(6)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    // Which anti-aim pattern a full window of samples shows, with the evidence for it
+    fn analyse_window(&self, samples: &[Sample]) -> Option<(Pattern, Value)> {
+        let min_spin_rate: f32 = get_parameter_value(&self.params, "min_spin_rate");
+        let min_spin_consistency: f32 = get_parameter_value(&self.params, "min_spin_consistency");
+        let min_jitter_amplitude: f32 = get_parameter_value(&self.params, "min_jitter_amplitude");
+        let max_jitter_period: f32 = get_parameter_value(&self.params, "max_jitter_period");
+        let min_jitter_power: f32 = get_parameter_value(&self.params, "min_jitter_power");
+        let min_jitter_autocorrelation: f32 =
+            get_parameter_value(&self.params, "min_jitter_autocorrelation");
+        let min_pitch_switches: i32 = get_parameter_value(&self.params, "min_pitch_switches");
+
+        let (first, last) = (samples.first()?, samples.last()?);
+        let duration = (last.1 - first.1).max(1) as f32 * TICK_INTERVAL;
+        // Simulation ticks per sample
+        let spacing = (last.1 - first.1) as f32 / (samples.len() - 1).max(1) as f32;
+        let yaw = unwrap_yaw(samples);
+
+        let total_rotation = yaw[yaw.len() - 1] - yaw[0];
+        let travelled: f32 = yaw.windows(2).map(|p| (p[1] - p[0]).abs()).sum();
+        let rate = total_rotation.abs() / duration;
+        if rate >= min_spin_rate && total_rotation.abs() >= travelled * min_spin_consistency {
+            return Some((
+                Pattern::Spinbot,
+                json!({
+                    "start_tick": first.0,
+                    "rotation_rate": rate,
+                    "direction": if total_rotation > 0.0 { "left" } else { "right" },
+                    "period": 360.0 / rate,
+                }),
+            ));
+        }
+
+        let residuals = detrend(&yaw);
+        let amplitude = residuals.iter().fold(f32::MIN, |a, b| a.max(*b))
+            - residuals.iter().fold(f32::MAX, |a, b| a.min(*b));
+        if amplitude >= min_jitter_amplitude {
+            if let Some((period, power)) = dominant_period(&residuals) {
+                let correlation = autocorrelation(&residuals, period.round() as usize);
+                let period_ticks = period * spacing;
+                if period_ticks <= max_jitter_period
+                    && power >= min_jitter_power
+                    && correlation >= min_jitter_autocorrelation
+                {
+                    return Some((
+                        Pattern::Jitter,
+                        json!({
+                            "start_tick": first.0,
+                            "amplitude": amplitude,
+                            "period_ticks": period_ticks,
+                            "period": period_ticks * TICK_INTERVAL,
+                            "power_share": power,
+                            "autocorrelation": correlation,
+                            "rotation_rate": travelled / duration,
+                        }),
+                    ));
+                }
+            }
+        }
+
+        let extremes: Vec<&Sample> = samples
+            .iter()
+            .filter(|s| s.3.abs() >= EXTREME_PITCH)
+            .collect();
+        let switches: Vec<u32> = extremes
+            .windows(2)
+            .filter(|p| p[0].3.signum() != p[1].3.signum())
+            .map(|p| p[1].1)
+            .collect();
+        if switches.len() as i32 >= min_pitch_switches {
+            let intervals: Vec<f32> = switches
+                .windows(2)
+                .map(|p| (p[1] - p[0]) as f32)
+                .collect();
+            return Some((
+                Pattern::FakePitch,
+                json!({
+                    "start_tick": first.0,
+                    "pitch_switches": switches.len(),
+                    // Ticks between switches, a full period is two switches
+                    "period_ticks": 2.0 * mean(&intervals),
+                    "rotation_rate": travelled / duration,
+                }),
+            ));
+        }
+
+        None
+    }
+
+    fn end_pattern(&self, steam_id: u64, tracker: &mut AntiAimTracker) -> Option<Detection> {
+        let ongoing = tracker.ongoing.take()?;
+        let field = |name: &str| -> Vec<f32> {
+            ongoing
+                .windows
+                .iter()
+                .filter_map(|w| w[name].as_f64())
+                .map(|v| v as f32)
+                .collect()
+        };
+        let (rates, periods) = (field("rotation_rate"), field("period_ticks"));
+        Some(Detection {
+            tick: ongoing.start_tick,
+            algorithm: self.algorithm_name().to_string(),
+            player: steam_id,
+            data: json!({
+                "type": match ongoing.pattern {
+                    Pattern::Spinbot => "spinbot",
+                    Pattern::Jitter => "jitter",
+                    Pattern::FakePitch => "fake_pitch",
+                },
+                "start_tick": ongoing.start_tick,
+                "end_tick": ongoing.end_tick,
+                "mean_rotation_rate": mean(&rates),
+                "mean_period_ticks": if periods.is_empty() { Value::Null } else { json!(mean(&periods)) },
+                "windows": ongoing.windows,
+            }),
+        })
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for AntiAim {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "anti_aim"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let window: i32 = get_parameter_value(&self.params, "window");
+        let window = window.max(4) as usize;
+        let ticknum = u32::from(state.tick);
+
+        let mut detections = Vec::new();
+        let mut trackers = std::mem::take(&mut self.trackers);
+
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            let tracked = player.in_pvs
+                && player.state == PlayerState::Alive
+                && info.steam_id != "BOT"
+                && !exempt(player);
+            if !tracked {
+                if let Some(tracker) = trackers.get_mut(&steam_id) {
+                    detections.extend(self.end_pattern(steam_id, tracker));
+                    tracker.samples.clear();
+                }
+                continue;
+            }
+
+            let tracker = trackers.entry(steam_id).or_default();
+            let last_sim_tick = tracker.samples.last().map_or(0, |s| s.1);
+            if player.simulation_tick <= last_sim_tick {
+                continue;
+            }
+            if player.simulation_tick > last_sim_tick + MAX_SAMPLE_GAP {
+                tracker.samples.clear();
+            }
+            tracker.samples.push((
+                ticknum,
+                player.simulation_tick,
+                player.view_angle,
+                player.pitch_angle,
+            ));
+            if tracker.samples.len() < window {
+                continue;
+            }
+
+            let samples = std::mem::take(&mut tracker.samples);
+            match self.analyse_window(&samples) {
+                Some((pattern, evidence)) => {
+                    if tracker.ongoing.as_ref().is_some_and(|o| o.pattern != pattern) {
+                        detections.extend(self.end_pattern(steam_id, tracker));
+                    }
+                    let ongoing = tracker.ongoing.get_or_insert(Ongoing {
+                        pattern,
+                        start_tick: samples[0].0,
+                        end_tick: ticknum,
+                        windows: Vec::new(),
+                    });
+                    ongoing.end_tick = ticknum;
+                    ongoing.windows.push(evidence);
+                }
+                None => detections.extend(self.end_pattern(steam_id, tracker)),
+            }
+        }
+
+        self.trackers = trackers;
+        Ok(detections)
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let mut trackers = std::mem::take(&mut self.trackers);
+        let mut detections = Vec::new();
+        for (steam_id, tracker) in trackers.iter_mut() {
+            detections.extend(self.end_pattern(*steam_id, tracker));
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
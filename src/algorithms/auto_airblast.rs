@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{
+    CheatAnalyserState, Class, Player, PlayerState, ProjectileType, Team, UserId,
+};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{angle_delta, angles_to, dot, length};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::gameevent_gen::GameEvent;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::demo::vector::Vector;
+use tf_demo_parser::ParserState;
+
+// Measures how quickly Pyros airblast incoming projectiles, counted from the tick the projectile first
+// came into airblast range, and how close the reflected projectile flies to the nearest enemy.
+// Auto-airblast cheats reflect on the first tick a projectile is reachable and usually aim the reflect
+// at an enemy for the player, so their reactions pile up at 0-1 ticks and their redirects are
+// near perfect. Humans react later and spread out, and anticipating a projectile doesn't make every
+// reflect land on the same tick. Good Pyros reflecting at close range redirect precisely too, so only
+// players who are both fast and precise are flagged, once at the end of the demo.
+
+// Only these fly straight after a reflect, so their direction can be compared with an enemy's
+fn flies_straight(ty: ProjectileType) -> bool {
+    matches!(
+        ty,
+        ProjectileType::Rocket
+            | ProjectileType::Flare
+            | ProjectileType::Arrow
+            | ProjectileType::HealingBolt
+    )
+}
+
+// How far the reflected projectile has to travel before its direction is measured
+const MIN_REDIRECT_DISTANCE: f32 = 16.0;
+// Ticks to wait for the reflected projectile to move before giving up
+const MAX_REDIRECT_TICKS: u32 = 10;
+
+fn is_enemy(team: Team, other: Team) -> bool {
+    matches!(team, Team::Red | Team::Blue) && matches!(other, Team::Red | Team::Blue) && team != other
+}
+
+struct PendingRedirect {
+    steam_id: u64,
+    team: Team,
+    projectile: EntityId,
+    tick: u32,
+    // Where the projectile was on the first tick after the reflect
+    start: Option<Vector>,
+}
+
+#[derive(Default)]
+struct AirblastStats {
+    reactions: Vec<Value>,
+    redirects: Vec<Value>,
+}
+
+#[derive(Default)]
+pub struct AutoAirblast {
+    params: Parameters,
+
+    // Projectile positions as of the previous tick
+    positions: HashMap<EntityId, Vector>,
+    // Tick an incoming projectile came into range of a Pyro, by (pyro, projectile)
+    in_range: HashMap<(EntityId, EntityId), u32>,
+    pending: Vec<PendingRedirect>,
+    stats: HashMap<u64, AirblastStats>,
+    // Tick of the positions on_tick sees. They are from the packet before the NetTick that runs it.
+    data_tick: u32,
+}
+
+impl AutoAirblast {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                // Distance from the Pyro's eyes at which a projectile can be reflected
+                ("airblast_range".to_string(), Parameter::Float(190.0)),
+                ("max_reaction_ticks".to_string(), Parameter::Int(1)),
+                ("min_fast_fraction".to_string(), Parameter::Float(0.7)),
+                ("max_redirect_angle".to_string(), Parameter::Float(2.0)),
+                ("min_precise_fraction".to_string(), Parameter::Float(0.7)),
+                ("min_airblasts".to_string(), Parameter::Int(10)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn resolve_redirects(&mut self, state: &CheatAnalyserState) {
+        let ticknum = u32::from(state.tick);
+        for mut redirect in std::mem::take(&mut self.pending) {
+            let Some(projectile) = state.projectiles.get(&redirect.projectile) else {
+                continue;
+            };
+            let Some(start) = redirect.start else {
+                redirect.start = Some(projectile.position);
+                self.pending.push(redirect);
+                continue;
+            };
+            let direction = projectile.position - start;
+            if length(direction) < MIN_REDIRECT_DISTANCE {
+                if ticknum <= redirect.tick + MAX_REDIRECT_TICKS {
+                    self.pending.push(redirect);
+                }
+                continue;
+            }
+
+            let flight = angles_to(start, projectile.position);
+            let target = state
+                .players
+                .iter()
+                .filter(|p| {
+                    p.in_pvs && p.state == PlayerState::Alive && is_enemy(redirect.team, p.team)
+                })
+                .map(|p| {
+                    let angle = angle_delta(flight, angles_to(start, p.body_position()))
+                        .min(angle_delta(flight, angles_to(start, p.eye_position())));
+                    (p, angle)
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            let Some((target, angle)) = target else {
+                continue;
+            };
+            self.stats.entry(redirect.steam_id).or_default().redirects.push(json!({
+                "tick": redirect.tick,
+                "projectile": format!("{:?}", projectile.ty),
+                "target": state.get_id64_from_entid(target.entity_id()),
+                "redirect_angle": angle,
+            }));
+        }
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for AutoAirblast {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "auto_airblast"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let airblast_range: f32 = get_parameter_value(&self.params, "airblast_range");
+        let ticknum = std::mem::replace(&mut self.data_tick, u32::from(state.tick));
+
+        self.resolve_redirects(state);
+
+        let pyros: Vec<&Player> = state
+            .players
+            .iter()
+            .filter(|p| p.in_pvs && p.state == PlayerState::Alive && p.class == Class::Pyro)
+            .collect();
+        self.in_range.retain(|(pyro, projectile), _| {
+            state.projectiles.contains_key(projectile) && pyros.iter().any(|p| p.entity_id() == *pyro)
+        });
+
+        for (id, projectile) in &state.projectiles {
+            let Some(previous) = self.positions.get(id) else {
+                continue;
+            };
+            let velocity = projectile.position - *previous;
+            for pyro in &pyros {
+                if !is_enemy(pyro.team, projectile.team) {
+                    continue;
+                }
+                let to_pyro = pyro.eye_position() - projectile.position;
+                if length(to_pyro) > airblast_range || dot(velocity, to_pyro) <= 0.0 {
+                    continue;
+                }
+                self.in_range.entry((pyro.entity_id(), *id)).or_insert(ticknum);
+            }
+        }
+
+        self.positions = state
+            .projectiles
+            .iter()
+            .map(|(id, p)| (*id, p.position))
+            .collect();
+        Ok(vec![])
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::GameEvent])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        _: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let Message::GameEvent(event_msg) = message else {
+            return Ok(vec![]);
+        };
+        let GameEvent::ObjectDeflected(deflect) = &event_msg.event else {
+            return Ok(vec![]);
+        };
+        // Pushing players also fires this event, without an object
+        if deflect.object_ent_index == 0 {
+            return Ok(vec![]);
+        }
+        let projectile_id = EntityId::from(deflect.object_ent_index as u32);
+        let user_id = UserId::from(deflect.user_id);
+        let Some(pyro) = state
+            .players
+            .iter()
+            .find(|p| p.info.as_ref().is_some_and(|info| info.user_id == user_id))
+        else {
+            return Ok(vec![]);
+        };
+        let Some(info) = &pyro.info else {
+            return Ok(vec![]);
+        };
+        if info.steam_id == "BOT" {
+            return Ok(vec![]);
+        }
+        let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+        let tick = u32::from(tick);
+        let Some(projectile) = state.projectiles.get(&projectile_id) else {
+            return Ok(vec![]);
+        };
+
+        if let Some(entered) = self.in_range.remove(&(pyro.entity_id(), projectile_id)) {
+            self.stats.entry(steam_id).or_default().reactions.push(json!({
+                "tick": tick,
+                "projectile": format!("{:?}", projectile.ty),
+                "reaction_ticks": tick as i64 - entered as i64,
+                "distance": length(pyro.eye_position() - projectile.position),
+            }));
+        }
+        if flies_straight(projectile.ty) {
+            self.pending.push(PendingRedirect {
+                steam_id,
+                team: pyro.team,
+                projectile: projectile_id,
+                tick,
+                start: None,
+            });
+        }
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let max_reaction_ticks: i32 = get_parameter_value(&self.params, "max_reaction_ticks");
+        let min_fast_fraction: f32 = get_parameter_value(&self.params, "min_fast_fraction");
+        let max_redirect_angle: f32 = get_parameter_value(&self.params, "max_redirect_angle");
+        let min_precise_fraction: f32 = get_parameter_value(&self.params, "min_precise_fraction");
+        let min_airblasts: i32 = get_parameter_value(&self.params, "min_airblasts");
+
+        let fraction = |values: &[Value], field: &str, max: f64| -> Option<f32> {
+            if (values.len() as i32) < min_airblasts {
+                return None;
+            }
+            let count = values
+                .iter()
+                .filter(|v| v[field].as_f64().is_some_and(|x| x <= max))
+                .count();
+            Some(count as f32 / values.len() as f32)
+        };
+
+        let mut detections = Vec::new();
+        for (steam_id, stats) in &self.stats {
+            let fast_fraction = fraction(&stats.reactions, "reaction_ticks", max_reaction_ticks as f64);
+            let precise_fraction =
+                fraction(&stats.redirects, "redirect_angle", max_redirect_angle as f64);
+            let fast = fast_fraction.is_some_and(|f| f >= min_fast_fraction);
+            let precise = precise_fraction.is_some_and(|f| f >= min_precise_fraction);
+            if !fast || !precise {
+                continue;
+            }
+
+            let mut reaction_ticks: Vec<i64> = stats
+                .reactions
+                .iter()
+                .filter_map(|r| r["reaction_ticks"].as_i64())
+                .collect();
+            reaction_ticks.sort();
+            let mut redirect_angles: Vec<f64> = stats
+                .redirects
+                .iter()
+                .filter_map(|r| r["redirect_angle"].as_f64())
+                .collect();
+            redirect_angles.sort_by(|a, b| a.total_cmp(b));
+
+            let first_tick = stats
+                .reactions
+                .iter()
+                .chain(&stats.redirects)
+                .filter_map(|v| v["tick"].as_u64())
+                .min()
+                .unwrap_or_default();
+            detections.push(Detection {
+                tick: first_tick as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "airblasts": stats.reactions.len(),
+                    "fast_fraction": fast_fraction,
+                    "median_reaction_ticks": reaction_ticks.get(reaction_ticks.len() / 2),
+                    "redirects": stats.redirects.len(),
+                    "precise_fraction": precise_fraction,
+                    "median_redirect_angle": redirect_angles.get(redirect_angles.len() / 2),
+                    "reactions": stats.reactions,
+                    "redirect_angles": stats.redirects,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
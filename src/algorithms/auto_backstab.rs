@@ -0,0 +1,272 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, Class, Player, PlayerState, Team};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{angle_delta, angle_diff, angles_to, is_backstab};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::gameevent_gen::GameEvent;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::ParserState;
+
+// Tracks every Spy holding a knife against every enemy, and remembers the first tick the Spy was both
+// in melee range and in a backstab position. Auto-backstab cheats stab on exactly that tick, usually
+// after snapping the view onto the victim (the knife only stabs if the Spy faces the victim).
+// A stab counts as suspicious when it lands within a tick of becoming possible and the view turned
+// sharply just before. Spies with repeated suspicious stabs are flagged once at the end.
+
+// TF_DMG_CUSTOM_BACKSTAB
+const DMG_CUSTOM_BACKSTAB: u16 = 2;
+// The Spy's view can't face further than this from the victim's (CTFKnife::IsBehindAndFacingTarget
+// requires the dot product of both forward vectors to be above -0.3)
+const MAX_FACING_DIFF: f32 = 107.5;
+// Spy views and distances kept per tick
+const HISTORY_TICKS: usize = 33;
+
+// Whether the Spy is behind the victim and facing them, following CTFKnife::IsBehindAndFacingTarget
+fn behind_and_facing(spy: &Player, victim: &Player) -> bool {
+    let (dx, dy) = (victim.position.x - spy.position.x, victim.position.y - spy.position.y);
+    let len = dx.hypot(dy);
+    if len <= 0.0 {
+        return false;
+    }
+    let (dx, dy) = (dx / len, dy / len);
+    let forward = |yaw: f32| {
+        let (sin, cos) = yaw.to_radians().sin_cos();
+        (cos, sin)
+    };
+    let (vx, vy) = forward(victim.view_angle);
+    let (sx, sy) = forward(spy.view_angle);
+    dx * vx + dy * vy > 0.0
+        && dx * sx + dy * sy > 0.5
+        && angle_diff(spy.view_angle, victim.view_angle).abs() < MAX_FACING_DIFF
+}
+
+fn distance_2d(a: &Player, b: &Player) -> f32 {
+    (a.position.x - b.position.x).hypot(a.position.y - b.position.y)
+}
+
+#[derive(Default)]
+struct PairTrack {
+    // First tick of the current stretch where the Spy could stab
+    stab_possible_since: Option<u32>,
+    distances: VecDeque<(u32, f32)>,
+}
+
+#[derive(Default)]
+pub struct AutoBackstab {
+    params: Parameters,
+
+    // (tick, yaw, pitch) of every Spy holding a knife
+    views: HashMap<EntityId, VecDeque<(u32, f32, f32)>>,
+    // By (spy, victim)
+    pairs: HashMap<(EntityId, EntityId), PairTrack>,
+    stabs: HashMap<u64, Vec<Value>>,
+    // Tick of the positions on_tick sees, which are from the packet before the NetTick that runs it
+    data_tick: u32,
+}
+
+impl AutoBackstab {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                // Horizontal distance between the Spy and the victim at which a stab can connect
+                ("melee_range".to_string(), Parameter::Float(72.0)),
+                ("max_delay_ticks".to_string(), Parameter::Int(1)),
+                // View change over the last few ticks before the stab
+                ("snap_ticks".to_string(), Parameter::Int(3)),
+                ("min_snap_angle".to_string(), Parameter::Float(30.0)),
+                ("min_suspicious_stabs".to_string(), Parameter::Int(3)),
+                ("min_suspicious_fraction".to_string(), Parameter::Float(0.5)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn holds_knife(state: &CheatAnalyserState, player: &Player) -> bool {
+        state
+            .get_active_weapon(player)
+            .is_some_and(|w| w.class_name == "CTFKnife")
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for AutoBackstab {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "auto_backstab"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let melee_range: f32 = get_parameter_value(&self.params, "melee_range");
+        let ticknum = std::mem::replace(&mut self.data_tick, u32::from(state.tick));
+
+        let alive = |p: &&Player| p.in_pvs && p.state == PlayerState::Alive;
+        let spies: Vec<&Player> = state
+            .players
+            .iter()
+            .filter(alive)
+            .filter(|p| p.class == Class::Spy && Self::holds_knife(state, p))
+            .collect();
+        self.views
+            .retain(|spy, _| spies.iter().any(|p| p.entity_id() == *spy));
+        self.pairs
+            .retain(|(spy, _), _| spies.iter().any(|p| p.entity_id() == *spy));
+
+        for spy in spies {
+            let views = self.views.entry(spy.entity_id()).or_default();
+            views.push_back((ticknum, spy.view_angle, spy.pitch_angle));
+            if views.len() > HISTORY_TICKS {
+                views.pop_front();
+            }
+
+            for victim in state.players.iter().filter(alive) {
+                if !matches!(victim.team, Team::Red | Team::Blue) || victim.team == spy.team {
+                    continue;
+                }
+                let distance = distance_2d(spy, victim);
+                let pair = self
+                    .pairs
+                    .entry((spy.entity_id(), victim.entity_id()))
+                    .or_default();
+                pair.distances.push_back((ticknum, distance));
+                if pair.distances.len() > HISTORY_TICKS {
+                    pair.distances.pop_front();
+                }
+                if distance <= melee_range && behind_and_facing(spy, victim) {
+                    pair.stab_possible_since.get_or_insert(ticknum);
+                } else {
+                    pair.stab_possible_since = None;
+                }
+            }
+        }
+
+        Ok(vec![])
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::GameEvent])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        _: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let snap_ticks: i32 = get_parameter_value(&self.params, "snap_ticks");
+        let max_delay_ticks: i32 = get_parameter_value(&self.params, "max_delay_ticks");
+        let min_snap_angle: f32 = get_parameter_value(&self.params, "min_snap_angle");
+
+        let Message::GameEvent(event_msg) = message else {
+            return Ok(vec![]);
+        };
+        let GameEvent::PlayerHurt(hurt) = &event_msg.event else {
+            return Ok(vec![]);
+        };
+        let find_player = |user_id: u16| {
+            state.players.iter().find(|p| {
+                p.info
+                    .as_ref()
+                    .is_some_and(|info| u16::from(info.user_id) == user_id)
+            })
+        };
+        let (Some(spy), Some(victim)) = (find_player(hurt.attacker), find_player(hurt.user_id)) else {
+            return Ok(vec![]);
+        };
+        if spy.entity_id() == victim.entity_id()
+            || !(hurt.custom == DMG_CUSTOM_BACKSTAB
+                || is_backstab(hurt.damage_amount, hurt.crit, hurt.weapon_id, victim.health))
+        {
+            return Ok(vec![]);
+        }
+        let Some(info) = &spy.info else {
+            return Ok(vec![]);
+        };
+        if info.steam_id == "BOT" {
+            return Ok(vec![]);
+        }
+        let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+        let tick = u32::from(tick);
+
+        let Some(pair) = self.pairs.get(&(spy.entity_id(), victim.entity_id())) else {
+            return Ok(vec![]);
+        };
+        let Some(views) = self.views.get(&spy.entity_id()) else {
+            return Ok(vec![]);
+        };
+        let (Some(before), Some(last)) = (
+            views.iter().rev().nth(snap_ticks.max(1) as usize),
+            views.back(),
+        ) else {
+            return Ok(vec![]);
+        };
+        let snap = angle_delta((before.1, before.2), (last.1, last.2));
+        let to_victim = angles_to(spy.eye_position(), victim.body_position());
+        let delay = pair.stab_possible_since.map(|since| tick as i64 - since as i64);
+        let suspicious =
+            delay.is_some_and(|d| d <= max_delay_ticks as i64) && snap >= min_snap_angle;
+
+        let distances: Vec<[f32; 2]> = pair
+            .distances
+            .iter()
+            .map(|(t, d)| [*t as f32, *d])
+            .collect();
+        self.stabs.entry(steam_id).or_default().push(json!({
+            "tick": tick,
+            "victim": state.get_id64_from_entid(victim.entity_id()),
+            // Ticks since the stab became possible, null if the stab position was never seen
+            "delay_ticks": delay,
+            "snap_angle": snap,
+            "view_to_victim_angle": angle_delta((last.1, last.2), to_victim),
+            "facing_diff": angle_diff(spy.view_angle, victim.view_angle).abs(),
+            "suspicious": suspicious,
+            "distance_history": distances,
+        }));
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_suspicious_stabs: i32 = get_parameter_value(&self.params, "min_suspicious_stabs");
+        let min_suspicious_fraction: f32 =
+            get_parameter_value(&self.params, "min_suspicious_fraction");
+
+        let mut detections = Vec::new();
+        for (steam_id, stabs) in &self.stabs {
+            let suspicious: Vec<&Value> = stabs
+                .iter()
+                .filter(|s| s["suspicious"].as_bool().unwrap_or_default())
+                .collect();
+            let fraction = suspicious.len() as f32 / stabs.len().max(1) as f32;
+            if (suspicious.len() as i32) < min_suspicious_stabs || fraction < min_suspicious_fraction
+            {
+                continue;
+            }
+            detections.push(Detection {
+                tick: suspicious[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "backstabs": stabs.len(),
+                    "suspicious_backstabs": suspicious.len(),
+                    "suspicious_fraction": fraction,
+                    "stabs": stabs,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerState, ProjectileType, Team};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{angle_delta, angles_to, length};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::demo::sendprop::SendPropIdentifier;
+use tf_demo_parser::demo::vector::Vector;
+use tf_demo_parser::ParserState;
+
+// Tracks every sticky bomb and measures how long its owner waits to detonate after an enemy walks into
+// its blast radius. Auto-detonation cheats detonate as soon as the first enemy is in range, so nearly
+// every detonation comes right after an entry, including stickies the Demoman isn't looking at.
+// A sticky counts as detonated when it disappears with an explosion next to it; stickies that are
+// shot down or removed when their owner dies don't explode. Players are flagged once at the end.
+
+// An explosion this close to where a sticky was counts as that sticky detonating
+const EXPLOSION_MATCH_DISTANCE: f32 = 48.0;
+
+struct StickyTrack {
+    owner: EntityId,
+    team: Team,
+    position: Vector,
+    spawn_tick: DemoTick,
+    // Tick every enemy currently inside the blast radius entered it
+    entries: HashMap<EntityId, u32>,
+}
+
+#[derive(Default)]
+pub struct AutoDetonation {
+    params: Parameters,
+
+    stickies: HashMap<EntityId, StickyTrack>,
+    // Explosions since the last tick
+    explosions: Vec<Vector>,
+    detonations: HashMap<u64, Vec<Value>>,
+}
+
+impl AutoDetonation {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("blast_radius".to_string(), Parameter::Float(146.0)),
+                // Detonations within this many ticks of an enemy entering count as instant. The demo only
+                // shows the detonation a tick after the command that caused it.
+                ("max_delay_ticks".to_string(), Parameter::Int(2)),
+                // Stickies further than this from the Demoman's crosshair are outside their view
+                ("max_view_angle".to_string(), Parameter::Float(55.0)),
+                ("min_detonations".to_string(), Parameter::Int(10)),
+                ("min_fast_fraction".to_string(), Parameter::Float(0.7)),
+                // Sticky trap Demomen detonate traps they can't see all the time, so fast detonations
+                // outside the view only count when there are many and they make up a large share of all
+                // detonations
+                ("min_out_of_view_detonations".to_string(), Parameter::Int(8)),
+                ("min_out_of_view_fraction".to_string(), Parameter::Float(0.3)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    // Record the detonation of a group of stickies from the same owner
+    fn detonated(&mut self, state: &CheatAnalyserState, owner: EntityId, stickies: &[StickyTrack]) {
+        let max_view_angle: f32 = get_parameter_value(&self.params, "max_view_angle");
+        let ticknum = u32::from(state.tick);
+
+        // The sticky whose first enemy in range entered last, which is what an auto-detonation reacts to
+        let Some((sticky, entered, enemy)) = stickies
+            .iter()
+            .filter_map(|s| {
+                let (enemy, entered) = s.entries.iter().min_by_key(|(_, tick)| **tick)?;
+                Some((s, *entered, *enemy))
+            })
+            .max_by_key(|(_, entered, _)| *entered)
+        else {
+            return;
+        };
+        let Some(player) = state.get_player_by_entid(owner) else {
+            return;
+        };
+        let Some(info) = &player.info else {
+            return;
+        };
+        if info.steam_id == "BOT" || player.state != PlayerState::Alive {
+            return;
+        }
+        let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+        let view_angle = angle_delta(
+            (player.view_angle, player.pitch_angle),
+            angles_to(player.eye_position(), sticky.position),
+        );
+        self.detonations.entry(steam_id).or_default().push(json!({
+            "tick": ticknum,
+            "delay_ticks": ticknum.saturating_sub(entered),
+            "stickies": stickies.len(),
+            "enemy": state.get_id64_from_entid(enemy),
+            "view_angle": view_angle,
+            "out_of_view": view_angle > max_view_angle,
+            "distance": length(sticky.position - player.eye_position()),
+        }));
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for AutoDetonation {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "auto_detonation"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let blast_radius: f32 = get_parameter_value(&self.params, "blast_radius");
+        let ticknum = u32::from(state.tick);
+
+        // Stickies that are gone (or whose entity was reused) since the last tick
+        let explosions = std::mem::take(&mut self.explosions);
+        let mut removed: HashMap<EntityId, Vec<StickyTrack>> = HashMap::new();
+        let ids: Vec<EntityId> = self.stickies.keys().copied().collect();
+        for id in ids {
+            let exists = state
+                .projectiles
+                .get(&id)
+                .is_some_and(|p| p.spawn_tick == self.stickies[&id].spawn_tick);
+            if exists {
+                continue;
+            }
+            let Some(sticky) = self.stickies.remove(&id) else {
+                continue;
+            };
+            if explosions
+                .iter()
+                .any(|e| length(*e - sticky.position) <= EXPLOSION_MATCH_DISTANCE)
+            {
+                removed.entry(sticky.owner).or_default().push(sticky);
+            }
+        }
+        for (owner, stickies) in removed {
+            self.detonated(state, owner, &stickies);
+        }
+
+        for (id, projectile) in &state.projectiles {
+            if projectile.ty != ProjectileType::Sticky {
+                continue;
+            }
+            let sticky = self.stickies.entry(*id).or_insert_with(|| StickyTrack {
+                owner: projectile.owner,
+                team: projectile.team,
+                position: projectile.position,
+                spawn_tick: projectile.spawn_tick,
+                entries: HashMap::new(),
+            });
+            sticky.position = projectile.position;
+            sticky.owner = projectile.owner;
+
+            let in_range: Vec<EntityId> = state
+                .players
+                .iter()
+                .filter(|p| {
+                    p.in_pvs
+                        && p.state == PlayerState::Alive
+                        && matches!(p.team, Team::Red | Team::Blue)
+                        && p.team != sticky.team
+                        && length(p.body_position() - sticky.position) <= blast_radius
+                })
+                .map(|p| p.entity_id())
+                .collect();
+            sticky.entries.retain(|enemy, _| in_range.contains(enemy));
+            for enemy in in_range {
+                sticky.entries.entry(enemy).or_insert(ticknum);
+            }
+        }
+
+        Ok(vec![])
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::TempEntities])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        _: &CheatAnalyserState,
+        parser_state: &ParserState,
+        _: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        const EXPLOSION_X: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TETFExplosion", "m_vecOrigin[0]");
+        const EXPLOSION_Y: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TETFExplosion", "m_vecOrigin[1]");
+        const EXPLOSION_Z: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TETFExplosion", "m_vecOrigin[2]");
+
+        let Message::TempEntities(msg) = message else {
+            return Ok(vec![]);
+        };
+        for event in &msg.events {
+            let class = &parser_state.server_classes[usize::from(event.class_id)].name;
+            if class.as_str() != "CTETFExplosion" {
+                continue;
+            }
+            let coord = |identifier| {
+                event
+                    .props
+                    .iter()
+                    .find(|p| p.identifier == identifier)
+                    .and_then(|p| f32::try_from(&p.value).ok())
+            };
+            if let (Some(x), Some(y), Some(z)) =
+                (coord(EXPLOSION_X), coord(EXPLOSION_Y), coord(EXPLOSION_Z))
+            {
+                self.explosions.push(Vector { x, y, z });
+            }
+        }
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let max_delay_ticks: i32 = get_parameter_value(&self.params, "max_delay_ticks");
+        let min_detonations: i32 = get_parameter_value(&self.params, "min_detonations");
+        let min_fast_fraction: f32 = get_parameter_value(&self.params, "min_fast_fraction");
+        let min_out_of_view_detonations: i32 =
+            get_parameter_value(&self.params, "min_out_of_view_detonations");
+        let min_out_of_view_fraction: f32 =
+            get_parameter_value(&self.params, "min_out_of_view_fraction");
+
+        let is_fast = |d: &Value| {
+            d["delay_ticks"]
+                .as_i64()
+                .is_some_and(|t| t <= max_delay_ticks as i64)
+        };
+        let is_out_of_view = |d: &Value| d["out_of_view"].as_bool().unwrap_or_default();
+
+        let mut detections = Vec::new();
+        for (steam_id, detonations) in &self.detonations {
+            let fast = detonations.iter().filter(|d| is_fast(d)).count();
+            let out_of_view = detonations.iter().filter(|d| is_out_of_view(d)).count();
+            let fast_out_of_view = detonations
+                .iter()
+                .filter(|d| is_fast(d) && is_out_of_view(d))
+                .count();
+            let fast_fraction = fast as f32 / detonations.len().max(1) as f32;
+            let out_of_view_fast_fraction = fast_out_of_view as f32 / out_of_view.max(1) as f32;
+            let fast_out_of_view_fraction =
+                fast_out_of_view as f32 / detonations.len().max(1) as f32;
+
+            let consistent = detonations.len() as i32 >= min_detonations
+                && fast_fraction >= min_fast_fraction;
+            let blind = fast_out_of_view as i32 >= min_out_of_view_detonations
+                && fast_out_of_view_fraction >= min_out_of_view_fraction
+                && out_of_view_fast_fraction >= min_fast_fraction;
+            if !consistent && !blind {
+                continue;
+            }
+
+            let mut delays: Vec<i64> = detonations
+                .iter()
+                .filter_map(|d| d["delay_ticks"].as_i64())
+                .collect();
+            delays.sort();
+            detections.push(Detection {
+                tick: detonations[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "detonations": detonations.len(),
+                    "fast_detonations": fast,
+                    "fast_fraction": fast_fraction,
+                    "median_delay_ticks": delays.get(delays.len() / 2),
+                    "out_of_view_detonations": out_of_view,
+                    "fast_out_of_view_detonations": fast_out_of_view,
+                    "fast_out_of_view_fraction": fast_out_of_view_fraction,
+                    "detonation_delays": detonations,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::base::cheat_analyser_base::{
+    CheatAnalyserState, Class, Medigun, Player, PlayerState, ProjectileType, Team,
+};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{dot, length, WEAPON_ID_SENTRY_ROCKET};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::gameevent_gen::GameEvent;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::demo::vector::Vector;
+use tf_demo_parser::ParserState;
+
+// Watches every Medic's medigun and records each Ubercharge pop, along with the damage and projectiles
+// that threatened the Medic or their heal target just before. Auto-uber cheats pop on the tick a hit
+// leaves the patient nearly dead, or a crit rocket comes close, and auto-vaccinator cheats switch the
+// resistance to whatever is about to hit. Humans pop early, late, or on a hunch, so a Medic whose pops
+// keep landing within a tick or two of lethal damage is flagged once at the end of the demo.
+
+const VACCINATOR_ITEM: u32 = 998;
+// Threats older than this are forgotten
+const THREAT_HISTORY_TICKS: u32 = 66;
+
+// Vaccinator resistances
+const RESIST_BULLET: u8 = 0;
+const RESIST_BLAST: u8 = 1;
+const RESIST_FIRE: u8 = 2;
+
+fn resist_name(resist: u8) -> &'static str {
+    match resist {
+        RESIST_BULLET => "bullet",
+        RESIST_BLAST => "blast",
+        RESIST_FIRE => "fire",
+        _ => "unknown",
+    }
+}
+
+// Which Vaccinator resistance blocks damage from a weapon, by player_hurt's weaponid. That is the weapon
+// that dealt the damage, even if the attacker has switched away from it since.
+fn weapon_resist(weapon_id: u16) -> u8 {
+    match weapon_id {
+        // Flamethrowers, flare guns, the Manmelter and the Dragon's Fury
+        25 | 58 | 84 | 109 => RESIST_FIRE,
+        // Rocket, grenade and sticky launchers, the Direct Hit, Cow Mangler, Loose Cannon and sentry
+        // rockets
+        22 | 23 | 24 | 65 | 79 | 91 | WEAPON_ID_SENTRY_ROCKET => RESIST_BLAST,
+        _ => RESIST_BULLET,
+    }
+}
+
+fn projectile_resist(ty: ProjectileType) -> u8 {
+    match ty {
+        ProjectileType::Rocket
+        | ProjectileType::Pipe
+        | ProjectileType::Sticky
+        | ProjectileType::LooseCannon => RESIST_BLAST,
+        ProjectileType::Flare => RESIST_FIRE,
+        _ => RESIST_BULLET,
+    }
+}
+
+// Rough base damage of a direct hit, used to guess whether a projectile would kill
+fn projectile_damage(ty: ProjectileType) -> f32 {
+    match ty {
+        ProjectileType::Rocket => 90.0,
+        ProjectileType::Pipe | ProjectileType::LooseCannon => 100.0,
+        ProjectileType::Sticky | ProjectileType::Arrow => 120.0,
+        ProjectileType::HealingBolt => 75.0,
+        ProjectileType::Flare => 30.0,
+        _ => 0.0,
+    }
+}
+
+fn is_enemy(team: Team, other: Team) -> bool {
+    matches!(team, Team::Red | Team::Blue) && matches!(other, Team::Red | Team::Blue) && team != other
+}
+
+struct Threat {
+    tick: u32,
+    // Player entity that was hit or is about to be
+    player: EntityId,
+    resist: u8,
+    lethal: bool,
+    data: Value,
+}
+
+#[derive(Default)]
+pub struct AutoUber {
+    params: Parameters,
+
+    // Medigun state as of the previous tick, by weapon entity
+    mediguns: HashMap<EntityId, Medigun>,
+    threats: VecDeque<Threat>,
+    // Projectile positions as of the previous tick
+    positions: HashMap<EntityId, Vector>,
+    // (projectile, player) pairs already recorded as a threat
+    seen_projectiles: HashSet<(EntityId, EntityId)>,
+    pops: HashMap<u64, Vec<Value>>,
+}
+
+impl AutoUber {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                // Pops within this many ticks of a lethal threat count as reactive. The demo only
+                // shows the pop a tick after the command that caused it.
+                ("max_reaction_ticks".to_string(), Parameter::Int(2)),
+                // A hit leaving the player at or below this much health counts as lethal
+                ("lethal_health".to_string(), Parameter::Int(50)),
+                // Incoming projectiles this close to the Medic or their patient count as a threat
+                ("threat_radius".to_string(), Parameter::Float(250.0)),
+                // Damage events listed with each pop
+                ("evidence_ticks".to_string(), Parameter::Int(33)),
+                ("min_reactive_pops".to_string(), Parameter::Int(3)),
+                ("min_reactive_fraction".to_string(), Parameter::Float(0.6)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn record_pop(
+        &mut self,
+        state: &CheatAnalyserState,
+        medic: &Player,
+        medigun: &Medigun,
+        kind: &str,
+        vaccinator: bool,
+    ) {
+        let max_reaction_ticks: i32 = get_parameter_value(&self.params, "max_reaction_ticks");
+        let evidence_ticks: i32 = get_parameter_value(&self.params, "evidence_ticks");
+        let ticknum = u32::from(state.tick);
+
+        let Some(info) = &medic.info else {
+            return;
+        };
+        if info.steam_id == "BOT" {
+            return;
+        }
+        let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+
+        let mut protected = vec![medic.entity_id()];
+        if medigun.healing {
+            protected.push(medigun.healing_target);
+        }
+        let relevant = |t: &&Threat| {
+            protected.contains(&t.player)
+                && t.tick <= ticknum
+                && ticknum - t.tick <= evidence_ticks.max(0) as u32
+        };
+        // The latest lethal threat the pop could be reacting to. The Vaccinator only helps against
+        // the damage type it resists.
+        let trigger = self
+            .threats
+            .iter()
+            .filter(relevant)
+            .filter(|t| t.lethal && (!vaccinator || t.resist == medigun.resist_type))
+            .max_by_key(|t| t.tick);
+        let reaction = trigger.map(|t| ticknum - t.tick);
+        let preceding: Vec<&Value> = self.threats.iter().filter(relevant).map(|t| &t.data).collect();
+
+        let pop = json!({
+            "tick": ticknum,
+            "kind": kind,
+            "resist": vaccinator.then(|| resist_name(medigun.resist_type)),
+            "patient": medigun
+                .healing
+                .then(|| state.get_id64_from_entid(medigun.healing_target))
+                .flatten(),
+            "reaction_ticks": reaction,
+            "reactive": reaction.is_some_and(|r| r as i32 <= max_reaction_ticks),
+            "preceding_damage": preceding,
+        });
+        self.pops.entry(steam_id).or_default().push(pop);
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for AutoUber {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "auto_uber"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let threat_radius: f32 = get_parameter_value(&self.params, "threat_radius");
+        let ticknum = u32::from(state.tick);
+
+        while self
+            .threats
+            .front()
+            .is_some_and(|t| t.tick + THREAT_HISTORY_TICKS < ticknum)
+        {
+            self.threats.pop_front();
+        }
+
+        let mut protected: Vec<&Player> = Vec::new();
+        let mut current = HashMap::new();
+        for (id, weapon) in &state.weapons {
+            let Some(medigun) = weapon.medigun else {
+                continue;
+            };
+            current.insert(*id, medigun);
+            let Some(medic) = state.get_player_by_entid(weapon.owner) else {
+                continue;
+            };
+            if !medic.in_pvs
+                || medic.state != PlayerState::Alive
+                || medic.class != Class::Medic
+                || medic.active_weapon != *id
+            {
+                continue;
+            }
+            protected.push(medic);
+            if medigun.healing {
+                protected.extend(state.get_player_by_entid(medigun.healing_target));
+            }
+
+            let Some(previous) = self.mediguns.get(id).copied() else {
+                continue;
+            };
+            let vaccinator = weapon.item_definition_index == VACCINATOR_ITEM;
+            if medigun.charge_release && !previous.charge_release {
+                self.record_pop(state, medic, &medigun, "uber", vaccinator);
+            } else if vaccinator && medigun.resist_type != previous.resist_type {
+                self.record_pop(state, medic, &medigun, "resist_switch", vaccinator);
+            }
+        }
+        self.mediguns = current;
+
+        // Incoming projectiles that would badly hurt a Medic or their patient
+        self.seen_projectiles
+            .retain(|(projectile, _)| state.projectiles.contains_key(projectile));
+        for (id, projectile) in &state.projectiles {
+            let Some(previous) = self.positions.get(id) else {
+                continue;
+            };
+            let velocity = projectile.position - *previous;
+            for player in &protected {
+                if !is_enemy(player.team, projectile.team)
+                    || self.seen_projectiles.contains(&(*id, player.entity_id()))
+                {
+                    continue;
+                }
+                let to_player = player.body_position() - projectile.position;
+                if length(to_player) > threat_radius || dot(velocity, to_player) <= 0.0 {
+                    continue;
+                }
+                self.seen_projectiles.insert((*id, player.entity_id()));
+                let multiplier = if projectile.critical { 3.0 } else { 1.0 };
+                let damage = projectile_damage(projectile.ty) * multiplier;
+                self.threats.push_back(Threat {
+                    tick: ticknum,
+                    player: player.entity_id(),
+                    resist: projectile_resist(projectile.ty),
+                    lethal: damage > 0.0 && damage >= player.health as f32,
+                    data: json!({
+                        "tick": ticknum,
+                        "projectile": format!("{:?}", projectile.ty),
+                        "critical": projectile.critical,
+                        "player": state.get_id64_from_entid(player.entity_id()),
+                        "attacker": state.get_id64_from_entid(projectile.owner),
+                        "health": player.health,
+                        "distance": length(to_player),
+                    }),
+                });
+            }
+        }
+        self.positions = state
+            .projectiles
+            .iter()
+            .map(|(id, p)| (*id, p.position))
+            .collect();
+
+        Ok(vec![])
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::GameEvent])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        _: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let lethal_health: i32 = get_parameter_value(&self.params, "lethal_health");
+
+        let Message::GameEvent(event_msg) = message else {
+            return Ok(vec![]);
+        };
+        let GameEvent::PlayerHurt(hurt) = &event_msg.event else {
+            return Ok(vec![]);
+        };
+        let find_player = |user_id: u16| {
+            state.players.iter().find(|p| {
+                p.info
+                    .as_ref()
+                    .is_some_and(|info| u16::from(info.user_id) == user_id)
+            })
+        };
+        let Some(victim) = find_player(hurt.user_id) else {
+            return Ok(vec![]);
+        };
+        let attacker = find_player(hurt.attacker);
+        let tick = u32::from(tick);
+
+        self.threats.push_back(Threat {
+            tick,
+            player: victim.entity_id(),
+            resist: weapon_resist(hurt.weapon_id),
+            lethal: (hurt.health as i32) <= lethal_health,
+            data: json!({
+                "tick": tick,
+                "damage": hurt.damage_amount,
+                "health": hurt.health,
+                "crit": hurt.crit,
+                "custom": hurt.custom,
+                "player": state.get_id64_from_entid(victim.entity_id()),
+                "attacker": attacker.and_then(|a| state.get_id64_from_entid(a.entity_id())),
+                "weapon_id": hurt.weapon_id,
+            }),
+        });
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_reactive_pops: i32 = get_parameter_value(&self.params, "min_reactive_pops");
+        let min_reactive_fraction: f32 = get_parameter_value(&self.params, "min_reactive_fraction");
+
+        let mut detections = Vec::new();
+        for (steam_id, pops) in &self.pops {
+            let reactive: Vec<&Value> = pops
+                .iter()
+                .filter(|p| p["reactive"].as_bool().unwrap_or_default())
+                .collect();
+            let fraction = reactive.len() as f32 / pops.len().max(1) as f32;
+            if (reactive.len() as i32) < min_reactive_pops || fraction < min_reactive_fraction {
+                continue;
+            }
+            let ubers = pops.iter().filter(|p| p["kind"] == "uber").count();
+            detections.push(Detection {
+                tick: reactive[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "pops": ubers,
+                    "resist_switches": pops.len() - ubers,
+                    "reactive_pops": reactive.len(),
+                    "reactive_fraction": fraction,
+                    "evidence": pops,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
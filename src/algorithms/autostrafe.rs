@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerCondition, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::viewangle_delta;
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::ParserState;
+
+// Autostrafe turns the view exactly as fast as the velocity vector can rotate while airborne.
+// For every airborne segment this compares the per tick yaw change with the rotation of the horizontal
+// velocity (sync: both turning the same way) and the speed gained with the best possible air strafe
+// (gain efficiency). Players whose jumps keep both above human levels are flagged once at the end.
+
+// sv_airaccelerate caps the wished speed in the air at 30 u/s, so the best possible strafe
+// adds 30 u/s perpendicular to the current velocity every tick
+const AIR_WISH_SPEED: f32 = 30.0;
+
+// Longer gaps between updates (death, leaving PVS) end the airborne segment
+const MAX_UPDATE_GAP: u32 = 8;
+
+fn optimal_speed(mut speed: f32, ticks: u32) -> f32 {
+    for _ in 0..ticks {
+        speed = (speed * speed + AIR_WISH_SPEED * AIR_WISH_SPEED).sqrt();
+    }
+    speed
+}
+
+#[derive(Default)]
+struct Segment {
+    start_tick: u32,
+    ticks: u32,
+    turning_ticks: u32,
+    synced_ticks: u32,
+    gained: f32,
+    optimal_gain: f32,
+}
+
+#[derive(Default)]
+struct StrafeTracker {
+    sim_tick: u32,
+    view_angle: f32,
+    pitch_angle: f32,
+    speed: f32,
+    direction: f32,
+    segment: Option<Segment>,
+    jumps: u32,
+    flagged_jumps: Vec<Value>,
+    sync_sum: f32,
+    efficiency_sum: f32,
+}
+
+#[derive(Default)]
+pub struct Autostrafe {
+    params: Parameters,
+
+    trackers: HashMap<u64, StrafeTracker>,
+}
+
+impl Autostrafe {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("min_sync".to_string(), Parameter::Float(0.9)),
+                ("min_gain_efficiency".to_string(), Parameter::Float(0.85)),
+                ("min_jumps".to_string(), Parameter::Int(20)),
+                ("min_segment_ticks".to_string(), Parameter::Int(20)),
+                // Velocity direction is too noisy to compare below this speed
+                ("min_speed".to_string(), Parameter::Float(150.0)),
+                // Yaw changes smaller than this per tick don't count as turning
+                ("min_turn".to_string(), Parameter::Float(0.1)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn end_segment(&self, tracker: &mut StrafeTracker) {
+        let Some(segment) = tracker.segment.take() else {
+            return;
+        };
+        let min_segment_ticks: i32 = get_parameter_value(&self.params, "min_segment_ticks");
+        if (segment.ticks as i32) < min_segment_ticks
+            || segment.turning_ticks == 0
+            || segment.optimal_gain <= 0.0
+        {
+            return;
+        }
+        let min_sync: f32 = get_parameter_value(&self.params, "min_sync");
+        let min_gain_efficiency: f32 = get_parameter_value(&self.params, "min_gain_efficiency");
+
+        let sync = segment.synced_ticks as f32 / segment.turning_ticks as f32;
+        let efficiency = segment.gained / segment.optimal_gain;
+        tracker.jumps += 1;
+        tracker.sync_sum += sync;
+        tracker.efficiency_sum += efficiency;
+        if sync >= min_sync && efficiency >= min_gain_efficiency {
+            tracker.flagged_jumps.push(json!({
+                "tick": segment.start_tick,
+                "airborne_ticks": segment.ticks,
+                "sync": sync,
+                "gain_efficiency": efficiency,
+                "speed_gained": segment.gained,
+            }));
+        }
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for Autostrafe {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "autostrafe"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let min_speed: f32 = get_parameter_value(&self.params, "min_speed");
+        let min_turn: f32 = get_parameter_value(&self.params, "min_turn");
+        let ticknum = u32::from(state.tick);
+
+        let mut trackers = std::mem::take(&mut self.trackers);
+
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            if !player.in_pvs || player.state != PlayerState::Alive || info.steam_id == "BOT" {
+                continue;
+            }
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+
+            let tracker = trackers.entry(steam_id).or_default();
+            if player.simulation_tick <= tracker.sim_tick {
+                continue;
+            }
+            let elapsed = player.simulation_tick - tracker.sim_tick;
+            let first_update = tracker.sim_tick == 0 || elapsed > MAX_UPDATE_GAP;
+            tracker.sim_tick = player.simulation_tick;
+
+            let speed = player.velocity.x.hypot(player.velocity.y);
+            let direction = player.velocity.y.atan2(player.velocity.x).to_degrees();
+            let (yaw_delta, _) = viewangle_delta(
+                player.view_angle,
+                player.pitch_angle,
+                tracker.view_angle,
+                tracker.pitch_angle,
+                elapsed,
+            );
+            let (direction_delta, _) =
+                viewangle_delta(direction, 0.0, tracker.direction, 0.0, elapsed);
+            let previous_speed = tracker.speed;
+
+            tracker.view_angle = player.view_angle;
+            tracker.pitch_angle = player.pitch_angle;
+            tracker.speed = speed;
+            tracker.direction = direction;
+
+            let airborne = !player.on_ground()
+                && !player.has_condition(PlayerCondition::BlastJumping)
+                && speed >= min_speed
+                && previous_speed >= min_speed;
+            if first_update || !airborne {
+                self.end_segment(tracker);
+                continue;
+            }
+
+            let segment = tracker.segment.get_or_insert_with(|| Segment {
+                start_tick: ticknum,
+                ..Default::default()
+            });
+            segment.ticks += elapsed;
+            segment.gained += speed - previous_speed;
+            segment.optimal_gain += optimal_speed(previous_speed, elapsed) - previous_speed;
+            if yaw_delta.abs() >= min_turn {
+                segment.turning_ticks += 1;
+                if yaw_delta.signum() == direction_delta.signum() {
+                    segment.synced_ticks += 1;
+                }
+            }
+        }
+
+        self.trackers = trackers;
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_jumps: i32 = get_parameter_value(&self.params, "min_jumps");
+
+        let mut trackers = std::mem::take(&mut self.trackers);
+        let mut detections = Vec::new();
+        for (steam_id, tracker) in trackers.iter_mut() {
+            self.end_segment(tracker);
+            if (tracker.flagged_jumps.len() as i32) < min_jumps {
+                continue;
+            }
+            detections.push(Detection {
+                tick: tracker.flagged_jumps[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "jumps": tracker.jumps,
+                    "flagged_jumps": tracker.flagged_jumps.len(),
+                    "mean_sync": tracker.sync_sum / tracker.jumps.max(1) as f32,
+                    "mean_gain_efficiency": tracker.efficiency_sum / tracker.jumps.max(1) as f32,
+                    "flagged": tracker.flagged_jumps,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
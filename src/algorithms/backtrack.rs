@@ -5,6 +5,8 @@ use crate::{base::cheat_analyser_base::{CheatAnalyserState}};
 
 use crate::lib::algorithm::{CheatAlgorithm, Detection};
 use crate::lib::parameters::{Parameter, Parameters, get_parameter_value};
+use crate::util::helpers::{angle_diff, is_backstab};
+use crate::util::items_game::item_json;
 
 use anyhow::Error;
 use itertools::any;
@@ -32,14 +34,6 @@ impl BackTrack {
     }
 }
 
-fn angle_diff(a: f32, b: f32) -> f32 {
-    (b - a + 180.0).rem_euclid(360.0) - 180.0
-}
-
-fn is_backstab(damage: u16, is_crit: bool, weapon_id: u16, victim_health: u16) -> bool {
-    return is_crit && weapon_id == 7 && (damage > 600 || damage as f32 > victim_health as f32 * 5.5);
-}
-
 impl<'a> CheatAlgorithm<'a> for BackTrack {
     fn default(&self) -> bool {
         true
@@ -152,6 +146,10 @@ impl<'a> CheatAlgorithm<'a> for BackTrack {
 
                         // ########################
 
+                        let weapon_item = state
+                            .get_active_weapon(attacker)
+                            .map_or(Value::Null, |w| item_json(w.item_definition_index));
+
                         let u200b = "​";
                         let data: Vec<(&str, Value)> = vec![
                             ("angle_attacker", Value::from(attacker.view_angle)),
@@ -164,6 +162,7 @@ impl<'a> CheatAlgorithm<'a> for BackTrack {
                             ("type", Value::from(if angle_diff > max_angle_diff && pos_diff < max_distance {"Angle" } else { "Distance"})),
                             ("victim_class", Value::from(victim.class.to_string())),
                             ("victim_health", Value::from(victim.health)),
+                            ("weapon", weapon_item),
                         ];
                         let mut new_data = Map::new();
                         for (i, (key, value)) in data.into_iter().enumerate() { new_data.insert(format!("{}{}", u200b.repeat(i), key), value); }
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerCondition, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+
+use anyhow::Error;
+use serde_json::json;
+use steamid_ng::SteamID;
+use tf_demo_parser::ParserState;
+
+// Finds chains of bunnyhops where the player keeps jumping again on the tick they land.
+// Landing and takeoff are timed in simulation ticks: the ground time of a hop is at most the number of
+// ticks between the last update descending through the air and the first update rising again.
+// This needs a demo with a snapshot every tick (a POV demo or tv_snapshotrate 66), coarser demos never
+// produce perfect hops.
+
+struct Chain {
+    start_tick: u32,
+    end_tick: u32,
+    ground_ticks: Vec<u32>,
+}
+
+#[derive(Default)]
+struct HopTracker {
+    sim_tick: u32,
+    // Simulation tick of the last update where the player was in the air and not rising
+    last_descending: Option<u32>,
+    rising: bool,
+    air_dash: u8,
+    chain: Option<Chain>,
+}
+
+#[derive(Default)]
+pub struct Bhop {
+    params: Parameters,
+
+    trackers: HashMap<u64, HopTracker>,
+}
+
+impl Bhop {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("min_chain_length".to_string(), Parameter::Int(10)),
+                ("min_perfect_ratio".to_string(), Parameter::Float(0.9)),
+                ("perfect_ground_ticks".to_string(), Parameter::Int(1)),
+                // Standing on the ground longer than this ends a chain
+                ("max_ground_ticks".to_string(), Parameter::Int(10)),
+                // Jumping gives about 289 u/s upwards
+                ("min_jump_velocity".to_string(), Parameter::Float(200.0)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn end_chain(&self, steam_id: u64, chain: Chain) -> Option<Detection> {
+        let min_chain_length: i32 = get_parameter_value(&self.params, "min_chain_length");
+        let min_perfect_ratio: f32 = get_parameter_value(&self.params, "min_perfect_ratio");
+        let perfect_ground_ticks: i32 = get_parameter_value(&self.params, "perfect_ground_ticks");
+
+        let hops = chain.ground_ticks.len();
+        let perfect = chain
+            .ground_ticks
+            .iter()
+            .filter(|ticks| **ticks as i32 <= perfect_ground_ticks)
+            .count();
+        let perfect_ratio = perfect as f32 / hops.max(1) as f32;
+        if (hops as i32) < min_chain_length || perfect_ratio < min_perfect_ratio {
+            return None;
+        }
+
+        Some(Detection {
+            tick: chain.start_tick,
+            algorithm: self.algorithm_name().to_string(),
+            player: steam_id,
+            data: json!({
+                "start_tick": chain.start_tick,
+                "end_tick": chain.end_tick,
+                "chain_length": hops,
+                "perfect_hops": perfect,
+                "perfect_ratio": perfect_ratio,
+                "ground_ticks": chain.ground_ticks,
+            }),
+        })
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for Bhop {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "bhop"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let max_ground_ticks: i32 = get_parameter_value(&self.params, "max_ground_ticks");
+        let min_jump_velocity: f32 = get_parameter_value(&self.params, "min_jump_velocity");
+        let ticknum = u32::from(state.tick);
+
+        let mut detections = Vec::new();
+        let mut tracked = Vec::new();
+
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            if !player.in_pvs || player.state != PlayerState::Alive || info.steam_id == "BOT" {
+                continue;
+            }
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            tracked.push(steam_id);
+
+            let tracker = self.trackers.entry(steam_id).or_default();
+            if player.simulation_tick <= tracker.sim_tick {
+                continue;
+            }
+            let sim_tick = player.simulation_tick;
+            tracker.sim_tick = sim_tick;
+
+            let air_dashed = player.air_dash > tracker.air_dash;
+            tracker.air_dash = player.air_dash;
+
+            // Explosive jumps and knockback aren't jumps
+            let mut broken = player.has_condition(PlayerCondition::BlastJumping) || air_dashed;
+
+            if player.on_ground() {
+                tracker.rising = false;
+                broken |= tracker
+                    .last_descending
+                    .is_some_and(|t| (sim_tick - t) as i32 > max_ground_ticks + 1);
+            } else if player.velocity.z > min_jump_velocity {
+                if !tracker.rising && !broken {
+                    if let Some(last_descending) = tracker.last_descending {
+                        let ground_ticks = sim_tick - last_descending - 1;
+                        if ground_ticks as i32 > max_ground_ticks {
+                            broken = true;
+                        } else {
+                            let chain = tracker.chain.get_or_insert_with(|| Chain {
+                                start_tick: ticknum,
+                                end_tick: ticknum,
+                                ground_ticks: Vec::new(),
+                            });
+                            chain.end_tick = ticknum;
+                            chain.ground_ticks.push(ground_ticks);
+                        }
+                    }
+                }
+                tracker.rising = true;
+            } else if player.velocity.z <= 0.0 {
+                tracker.rising = false;
+                tracker.last_descending = Some(sim_tick);
+            }
+
+            if broken {
+                tracker.last_descending = None;
+                if let Some(chain) = tracker.chain.take() {
+                    detections.extend(self.end_chain(steam_id, chain));
+                }
+            }
+        }
+
+        let stale: Vec<u64> = self
+            .trackers
+            .keys()
+            .filter(|steam_id| !tracked.contains(steam_id))
+            .copied()
+            .collect();
+        for steam_id in stale {
+            if let Some(chain) = self.trackers.remove(&steam_id).and_then(|t| t.chain) {
+                detections.extend(self.end_chain(steam_id, chain));
+            }
+        }
+
+        Ok(detections)
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let mut detections = Vec::new();
+        for (steam_id, tracker) in std::mem::take(&mut self.trackers) {
+            if let Some(chain) = tracker.chain {
+                detections.extend(self.end_chain(steam_id, chain));
+            }
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, Class, Player, PlayerCondition, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::aim_targets::AimTarget;
+use crate::util::helpers::angles_to;
+use crate::util::shots::shots_fired;
+use crate::util::visibility::{line_of_sight, on_target, AimTracker, CloakTracker, Track};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::ParserState;
+
+// Compares how often players keep their crosshair on (and shoot at) fully cloaked enemy Spies with how
+// often they do so for enemies they can see. A Spy only counts as invisible once it has been cloaked
+// for a while without flickering, so following a Spy that was just bumped or shot doesn't count.
+// Legit players lose track of a Spy soon after it disappears, so their aim rate on cloaked Spies is a
+// small fraction of their rate on visible enemies. ESP draws cloaked Spies like anyone else, so
+// players using it track them nearly as often. Players are flagged once at the end of the demo.
+
+#[derive(Default)]
+struct AwarenessStats {
+    // Tracks note whether the Spy was disguised
+    tracker: AimTracker<bool>,
+    // Ticks a cloaked Spy / visible enemy was within the engagement fov
+    cloaked_exposure_ticks: u32,
+    visible_exposure_ticks: u32,
+    // Ticks the crosshair was on a cloaked Spy / visible enemy
+    cloaked_aim_ticks: u32,
+    visible_aim_ticks: u32,
+    cloaked_shots: u32,
+    visible_shots: u32,
+    tracks: Vec<Value>,
+    shots: Vec<Value>,
+}
+
+#[derive(Default)]
+pub struct CloakedSpy {
+    params: Parameters,
+
+    cloaks: CloakTracker,
+    stats: HashMap<u64, AwarenessStats>,
+    // Attacks (steam id, tick) seen since the last tick, resolved once the positions of that tick are
+    // known
+    pending: Vec<(u64, u32)>,
+}
+
+impl CloakedSpy {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                // Ticks a Spy has to stay cloaked, without flickering, to count as invisible
+                ("min_cloaked_ticks".to_string(), Parameter::Int(100)),
+                // Crosshair this close to a Spy's head or body centre, or over its body, is aiming at it
+                ("max_aim_angle".to_string(), Parameter::Float(4.0)),
+                ("body_radius".to_string(), Parameter::Float(24.0)),
+                ("engagement_fov".to_string(), Parameter::Float(30.0)),
+                ("min_track_ticks".to_string(), Parameter::Int(33)),
+                // How far the Spy has to move across the view during a track
+                ("min_bearing_change".to_string(), Parameter::Float(10.0)),
+                ("min_tracks".to_string(), Parameter::Int(3)),
+                // Aim rate on cloaked Spies relative to the aim rate on visible enemies
+                ("min_rate_ratio".to_string(), Parameter::Float(0.5)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn on_target(&self, target: &AimTarget) -> bool {
+        let max_aim_angle: f32 = get_parameter_value(&self.params, "max_aim_angle");
+        let body_radius: f32 = get_parameter_value(&self.params, "body_radius");
+        on_target(target, max_aim_angle, body_radius)
+    }
+
+    fn invisible_spy(&self, spy: &Player) -> bool {
+        let min_cloaked_ticks: i32 = get_parameter_value(&self.params, "min_cloaked_ticks");
+        spy.class == Class::Spy
+            && self
+                .cloaks
+                .cloaked_ticks(spy)
+                .is_some_and(|ticks| ticks as i32 >= min_cloaked_ticks)
+    }
+
+    // Keep the track if it followed the Spy long enough
+    fn close_track(
+        &self,
+        stats: &mut AwarenessStats,
+        state: &CheatAnalyserState,
+        track: Option<Track<bool>>,
+    ) {
+        let min_track_ticks: i32 = get_parameter_value(&self.params, "min_track_ticks");
+        let min_bearing_change: f32 = get_parameter_value(&self.params, "min_bearing_change");
+
+        let Some(track) = track else {
+            return;
+        };
+        if !track.is_tracking(min_track_ticks.max(0) as u32, min_bearing_change) {
+            return;
+        }
+        stats.tracks.push(json!({
+            "tick": track.start,
+            "spy": state.get_id64_from_entid(track.target),
+            "ticks": track.ticks,
+            "bearing_change": track.bearing_change(),
+            "mean_error": track.mean_error(),
+            "disguised": track.info,
+        }));
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for CloakedSpy {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "cloaked_spy"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let engagement_fov: f32 = get_parameter_value(&self.params, "engagement_fov");
+        let ticknum = u32::from(state.tick);
+
+        self.cloaks.update(state);
+
+        let mut stats = std::mem::take(&mut self.stats);
+        // The enemy each player's crosshair is on, and whether it is an invisible Spy
+        let mut aimed: HashMap<u64, (EntityId, bool)> = HashMap::new();
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            if info.steam_id == "BOT" {
+                continue;
+            }
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            let player_stats = stats.entry(steam_id).or_default();
+            if !player.in_pvs || player.state != PlayerState::Alive {
+                let track = player_stats.tracker.end();
+                self.close_track(player_stats, state, track);
+                continue;
+            }
+
+            // Enemies behind walls are neither visible nor worth counting as cloaked
+            let targets: Vec<(AimTarget, &Player, bool)> = state
+                .get_aim(player)
+                .into_iter()
+                .flat_map(|aim| aim.targets.iter().copied())
+                .filter_map(|t| {
+                    let other = state.get_player_by_entid(t.entity)?;
+                    if !line_of_sight(state, player, other) {
+                        return None;
+                    }
+                    if self.invisible_spy(other) {
+                        return Some((t, other, true));
+                    }
+                    // Spies still fading out are neither visible nor invisible
+                    self.cloaks.cloaked_ticks(other).is_none().then_some((t, other, false))
+                })
+                .collect();
+            let in_fov = |cloaked: bool| {
+                targets
+                    .iter()
+                    .any(|(t, _, c)| *c == cloaked && t.angle() <= engagement_fov)
+            };
+            player_stats.cloaked_exposure_ticks += u32::from(in_fov(true));
+            player_stats.visible_exposure_ticks += u32::from(in_fov(false));
+
+            let closest = targets
+                .iter()
+                .min_by(|a, b| a.0.angle().total_cmp(&b.0.angle()))
+                .filter(|(t, _, _)| self.on_target(t));
+            let Some((target, other, cloaked)) = closest else {
+                let track = player_stats.tracker.end();
+                self.close_track(player_stats, state, track);
+                continue;
+            };
+            aimed.insert(steam_id, (target.entity, *cloaked));
+            if !cloaked {
+                player_stats.visible_aim_ticks += 1;
+                let track = player_stats.tracker.end();
+                self.close_track(player_stats, state, track);
+                continue;
+            }
+            player_stats.cloaked_aim_ticks += 1;
+            let bearing = angles_to(player.eye_position(), other.body_position());
+            let disguised = other.has_condition(PlayerCondition::Disguised);
+            let ended = player_stats
+                .tracker
+                .follow(target.entity, ticknum, bearing, target.angle(), disguised);
+            self.close_track(player_stats, state, ended);
+        }
+
+        for (steam_id, tick) in std::mem::take(&mut self.pending) {
+            let (Some(player_stats), Some((target, cloaked))) =
+                (stats.get_mut(&steam_id), aimed.get(&steam_id))
+            else {
+                continue;
+            };
+            if !cloaked {
+                player_stats.visible_shots += 1;
+                continue;
+            }
+            player_stats.cloaked_shots += 1;
+            player_stats.shots.push(json!({
+                "tick": tick,
+                "spy": state.get_id64_from_entid(*target),
+            }));
+        }
+        self.stats = stats;
+
+        Ok(vec![])
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::TempEntities])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        parser_state: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let Message::TempEntities(msg) = message else {
+            return Ok(vec![]);
+        };
+        let tick = u32::from(tick);
+        for shot in shots_fired(msg, parser_state) {
+            if !shot.is_attack() {
+                continue;
+            }
+            let Some(info) = state
+                .get_player_by_entid(shot.entity)
+                .and_then(|p| p.info.as_ref())
+            else {
+                continue;
+            };
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            // Hitscan shots come with both bullets and an animation
+            if !self.pending.contains(&(steam_id, tick)) {
+                self.pending.push((steam_id, tick));
+            }
+        }
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_tracks: i32 = get_parameter_value(&self.params, "min_tracks");
+        let min_rate_ratio: f32 = get_parameter_value(&self.params, "min_rate_ratio");
+
+        let mut detections = Vec::new();
+        for (steam_id, stats) in &self.stats {
+            let cloaked_rate =
+                stats.cloaked_aim_ticks as f32 / stats.cloaked_exposure_ticks.max(1) as f32;
+            let visible_rate =
+                stats.visible_aim_ticks as f32 / stats.visible_exposure_ticks.max(1) as f32;
+            let ratio = cloaked_rate / visible_rate.max(f32::EPSILON);
+            if (stats.tracks.len() as i32) < min_tracks || ratio < min_rate_ratio {
+                continue;
+            }
+            detections.push(Detection {
+                tick: stats.tracks[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "cloaked_aim_rate": cloaked_rate,
+                    "visible_aim_rate": visible_rate,
+                    "rate_ratio": ratio,
+                    "cloaked_exposure_ticks": stats.cloaked_exposure_ticks,
+                    "cloaked_aim_ticks": stats.cloaked_aim_ticks,
+                    "visible_exposure_ticks": stats.visible_exposure_ticks,
+                    "visible_aim_ticks": stats.visible_aim_ticks,
+                    "cloaked_shots": stats.cloaked_shots,
+                    "visible_shots": stats.visible_shots,
+                    "cloaked_tracks": stats.tracks,
+                    "shots_at_cloaked": stats.shots,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
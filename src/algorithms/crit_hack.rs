@@ -0,0 +1,666 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, Player, PlayerCondition, Weapon};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{is_sentry_damage, weapon_id};
+use crate::util::items_game::{get_item, item_json};
+use crate::util::shots::shots_fired;
+
+use anyhow::Error;
+use serde_json::json;
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::gameevent_gen::GameEvent;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::tempentities::TempEntitiesMessage;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::ParserState;
+
+// Compares how often each player's hits are random crits with TF2's crit chance model.
+// Every shot rolls a 2% chance (15% for melee), multiplied by up to 4 depending on the damage dealt over
+// the last 20 seconds. Rapid fire weapons instead roll once per second for a 2 second crit stream, so
+// they're counted per roll rather than per hit. Crit hacks pick every roll that would crit, pushing the
+// crit rate up to the server's cap: the server denies ranged random crits while the player's observed
+// crit rate this round (crit damage normalised by the crit multiplier, over all ranged damage) is more
+// than 10% above their current crit chance. Hits while a player is over the cap can't be random crits,
+// so they aren't trials, and crits landed over it are reported separately. The chance of seeing at
+// least that many crits legitimately is reported as a p-value, over all weapons a player used.
+// Guaranteed crits are left out: crit boosts (Kritzkrieg, first blood, ...), backstabs, headshots, and
+// every hit where a mini-crit could have applied. Damage is only credited to the weapon that dealt it,
+// so sentry damage and projectiles landing after a weapon switch are skipped. Which weapons crit on
+// conditions (Market Gardener, Axtinguisher, ...) comes from items_game.txt, so nothing is checked
+// without it.
+// Ranged weapons also pay for their random crits from a bucket: every attack, hit or miss, adds its
+// damage up to a cap, and a crit costs three times its damage, up to three times more again the larger
+// the share of the weapon's rolls that asked for a crit. The bucket is filled from the attacks in the
+// demo. Hits the bucket couldn't have paid a crit for aren't trials, and crits it couldn't have paid for
+// are reported like the ones over the cap. A player's attacks can't be seen while they're out of view,
+// so their buckets are assumed full then, and when a weapon is first seen.
+
+const TICK_INTERVAL: f32 = 0.015;
+
+// Source: tf_shareddefs.h and CTFWeaponBase::CalcIsAttackCriticalHelper
+const CRIT_CHANCE: f32 = 0.02;
+const CRIT_CHANCE_MELEE: f32 = 0.15;
+const CRITMOD_DAMAGE: f32 = 800.0;
+const CRITMOD_MAX_MULT: f32 = 4.0;
+const CRITMOD_TIME: f32 = 20.0;
+const CRIT_DURATION_RAPID: f32 = 2.0;
+const CRIT_ROLL_INTERVAL_RAPID: f32 = 1.0;
+// Ranged random crits are denied while the observed crit rate is this far above the crit chance
+const CRIT_CAP_MARGIN: f32 = 0.1;
+const CRIT_DAMAGE_MULT: f32 = 3.0;
+// Source: tf_weapon_criticals_bucket_cap and CTFWeaponBase::IsAllowedToWithdrawFromCritBucket
+const CRIT_BUCKET_CAP: f32 = 1000.0;
+// Crits cost up to this much more as the share of rolls asking for one goes from 10% to 100%
+const CRIT_COST_MAX_MULT: f32 = 3.0;
+
+// TF_DMG_CUSTOM_* values that are crits regardless of the crit roll, or can't crit at all
+const DMG_CUSTOM_HEADSHOT: u16 = 1;
+const DMG_CUSTOM_BACKSTAB: u16 = 2;
+const DMG_CUSTOM_BURNING: u16 = 3;
+const DMG_CUSTOM_BURNING_FLARE: u16 = 8;
+const DMG_CUSTOM_BURNING_ARROW: u16 = 17;
+const DMG_CUSTOM_SHOTGUN_REVENGE_CRIT: u16 = 32;
+const DMG_CUSTOM_BLEEDING: u16 = 34;
+const DMG_CUSTOM_HEADSHOT_DECAPITATION: u16 = 51;
+
+#[derive(Clone, Copy, PartialEq)]
+enum CritKind {
+    Normal,
+    Melee,
+    // Minigun, SMG, pistols, flamethrowers and syringe guns crit in streams
+    Rapid,
+}
+
+fn crit_kind(class_name: &str) -> Option<CritKind> {
+    Some(match class_name {
+        // Knives only crit on backstabs, sniper rifles and bows on headshots, and the Manmelter on
+        // collected crits
+        "CTFKnife" | "CTFSniperRifle" | "CTFSniperRifleDecap" | "CTFSniperRifleClassic"
+        | "CTFCompoundBow" | "CTFFlareGun_Revenge" => return None,
+        "CTFMinigun" | "CTFSMG" | "CTFPistol" | "CTFPistol_Scout" | "CTFFlameThrower"
+        | "CTFSyringeGun" => CritKind::Rapid,
+        "CTFBat" | "CTFBat_Wood" | "CTFBat_Fish" | "CTFBat_Giftwrap" | "CTFShovel" | "CTFBottle"
+        | "CTFStickBomb" | "CTFSword" | "CTFKatana" | "CTFFireAxe" | "CTFFists" | "CTFBonesaw"
+        | "CTFWrench" | "CTFRobotArm" | "CTFClub" | "CTFBreakableMelee" | "CTFBreakableSign"
+        | "CTFSlap" => CritKind::Melee,
+        _ => CritKind::Normal,
+    })
+}
+
+// Base damage of one attack (all bullets, or the projectile) and refire delay of ranged weapons, from
+// the weapon scripts. Weapons whose damage isn't fixed by their script (flamethrowers, the Bison, ...)
+// aren't listed, so their crit bucket isn't modelled.
+fn base_attack(class_name: &str) -> Option<(f32, f32)> {
+    Some(match class_name {
+        "CTFScatterGun" | "CTFSodaPopper" | "CTFPEPBrawlerBlaster" | "CTFShotgun"
+        | "CTFShotgun_Soldier" | "CTFShotgun_HWG" | "CTFShotgun_Pyro" => (60.0, 0.625),
+        "CTFPistol" | "CTFPistol_Scout" | "CTFPistol_ScoutSecondary" => (15.0, 0.15),
+        "CTFPistol_ScoutPrimary" => (48.0, 0.36),
+        "CTFMinigun" => (36.0, 0.1),
+        "CTFSMG" => (8.0, 0.1),
+        "CTFSyringeGun" => (10.0, 0.1),
+        "CTFRevolver" => (40.0, 0.5),
+        "CTFRocketLauncher" | "CTFRocketLauncher_DirectHit" | "CTFRocketLauncher_AirStrike" => {
+            (90.0, 0.8)
+        }
+        "CTFGrenadeLauncher" | "CTFCannon" => (100.0, 0.6),
+        "CTFPipebombLauncher" => (120.0, 0.6),
+        "CTFFlareGun" => (30.0, 2.0),
+        _ => return None,
+    })
+}
+
+// Damage one attack of this weapon adds to its crit bucket, and its refire delay
+fn attack_damage(weapon: &Weapon) -> Option<(f32, f32)> {
+    let (damage, fire_delay) = base_attack(&weapon.class_name)?;
+    let item = get_item(weapon.item_definition_index)?;
+    Some((damage * item.damage_multiplier(), fire_delay))
+}
+
+// Damage a crit is paid for with: one attack, or a whole crit stream for rapid fire weapons
+fn crit_damage(weapon: &Weapon, kind: CritKind) -> Option<f32> {
+    let (damage, fire_delay) = attack_damage(weapon)?;
+    Some(match kind {
+        CritKind::Rapid => {
+            (damage * CRIT_DURATION_RAPID / fire_delay).min(CRIT_BUCKET_CAP / CRIT_DAMAGE_MULT)
+        }
+        _ => damage,
+    })
+}
+
+fn crit_boosted(player: &Player) -> bool {
+    use PlayerCondition::*;
+    [
+        CritBoosted,
+        CritBoostedPumpkin,
+        CritBoostedUserBuff,
+        CritBoostedDemoCharge,
+        CritBoostedFirstBlood,
+        CritBoostedBonusTime,
+        CritBoostedCtfCapture,
+        CritBoostedOnKill,
+        CritBoostedRageBuff,
+        CritBoostedCardEffect,
+        CritBoostedRuneTemp,
+    ]
+    .into_iter()
+    .any(|cond| player.has_condition(cond))
+}
+
+fn mini_crit_boosted(player: &Player) -> bool {
+    use PlayerCondition::*;
+    [OffensiveBuff, EnergyBuff, MiniCritBoostedOnKill]
+        .into_iter()
+        .any(|cond| player.has_condition(cond))
+}
+
+// Mini-crits on the victim, or buffs that block crits against them
+fn victim_excluded(player: &Player) -> bool {
+    use PlayerCondition::*;
+    [Urine, MarkedForDeath, MarkedForDeathSilent, DefensiveBuff, DefenseBuffHigh]
+        .into_iter()
+        .any(|cond| player.has_condition(cond))
+}
+
+// Probability of at least `k` successes in independent trials with the given probabilities
+fn upper_tail(probabilities: &[f64], k: usize) -> f64 {
+    if k == 0 {
+        return 1.0;
+    }
+    // dist[i] = P(i successes so far), with everything from k upwards collected in dist[k]
+    let mut dist = vec![0.0; k + 1];
+    dist[0] = 1.0;
+    for p in probabilities {
+        dist[k] += dist[k - 1] * p;
+        for i in (1..k).rev() {
+            dist[i] = dist[i] * (1.0 - p) + dist[i - 1] * p;
+        }
+        dist[0] *= 1.0 - p;
+    }
+    dist[k].clamp(0.0, 1.0)
+}
+
+struct CritBucket {
+    item_definition_index: u32,
+    tokens: f32,
+    // Random crit rolls, and how many of them asked for a crit
+    checks: u32,
+    seed_requests: u32,
+    last_attack: u32,
+    // Rapid fire weapons roll once a second
+    last_roll: Option<u32>,
+}
+
+impl CritBucket {
+    fn new(item_definition_index: u32) -> Self {
+        Self {
+            item_definition_index,
+            tokens: CRIT_BUCKET_CAP,
+            checks: 0,
+            seed_requests: 0,
+            last_attack: 0,
+            last_roll: None,
+        }
+    }
+
+    // What asking for the next crit costs
+    fn cost(&self, damage: f32) -> f32 {
+        let mult = if self.checks == 0 {
+            1.0
+        } else {
+            let requested = ((self.seed_requests + 1) as f32 / self.checks as f32).clamp(0.1, 1.0);
+            1.0 + (CRIT_COST_MAX_MULT - 1.0) * (requested - 0.1) / 0.9
+        };
+        damage * CRIT_DAMAGE_MULT * mult
+    }
+
+    // Pay for a crit, if there's enough in the bucket
+    fn withdraw(&mut self, damage: f32) -> bool {
+        let cost = self.cost(damage);
+        self.seed_requests += 1;
+        if cost > self.tokens {
+            return false;
+        }
+        self.tokens -= cost;
+        true
+    }
+}
+
+struct Trial {
+    // Tick for single shots, roll interval for rapid fire weapons
+    slot: u32,
+    chance: f64,
+    crit: bool,
+}
+
+struct WeaponStats {
+    item_definition_index: u32,
+    kind: CritKind,
+    trials: Vec<Trial>,
+    crit_ticks: Vec<u32>,
+    // Crits landed while the player was over the crit cap
+    capped_crit_ticks: Vec<u32>,
+    // Crits the weapon's crit bucket couldn't have paid for
+    overdrawn_crit_ticks: Vec<u32>,
+    // End of the current crit stream of a rapid fire weapon
+    stream_until: u32,
+}
+
+impl WeaponStats {
+    fn crits(&self) -> usize {
+        self.trials.iter().filter(|t| t.crit).count()
+    }
+
+    fn expected(&self) -> f64 {
+        self.trials.iter().map(|t| t.chance).sum()
+    }
+}
+
+#[derive(Default)]
+pub struct CritHack {
+    params: Parameters,
+
+    // Damage dealt by every player over the last 20 seconds, (tick, damage)
+    recent_damage: HashMap<u64, VecDeque<(u32, u16)>>,
+    // Ranged damage this round, (random crit damage, all damage)
+    ranged_damage: HashMap<u64, (f32, f32)>,
+    // By weapon entity
+    buckets: HashMap<EntityId, CritBucket>,
+    stats: HashMap<(u64, u32), WeaponStats>,
+}
+
+impl CritHack {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("min_trials".to_string(), Parameter::Int(50)),
+                ("max_p_value".to_string(), Parameter::Float(0.0001)),
+                // Building damage isn't in player_hurt, so the observed crit rate can read a little high
+                ("min_capped_crits".to_string(), Parameter::Int(3)),
+                // Damage attributes that only apply sometimes (range, health, ...) aren't modelled
+                ("min_overdrawn_crits".to_string(), Parameter::Int(3)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    // Crit multiplier from the damage the player dealt recently
+    fn crit_mult(&mut self, steam_id: u64, tick: u32) -> f32 {
+        let window = (CRITMOD_TIME / TICK_INTERVAL) as u32;
+        let damage = self.recent_damage.entry(steam_id).or_default();
+        while damage.front().is_some_and(|(t, _)| t + window < tick) {
+            damage.pop_front();
+        }
+        let total: f32 = damage.iter().map(|(_, d)| *d as f32).sum();
+        1.0 + (CRITMOD_MAX_MULT - 1.0) * (total / CRITMOD_DAMAGE).min(1.0)
+    }
+
+    // Every attack of a ranged weapon adds its damage to the weapon's bucket, and rolls for a crit
+    fn fill_buckets(
+        &mut self,
+        msg: &TempEntitiesMessage,
+        state: &CheatAnalyserState,
+        parser_state: &ParserState,
+        tick: u32,
+    ) {
+        let roll_interval = (CRIT_ROLL_INTERVAL_RAPID / TICK_INTERVAL) as u32;
+        for shot in shots_fired(msg, parser_state) {
+            if !shot.is_attack() {
+                continue;
+            }
+            let Some(weapon) = state
+                .get_player_by_entid(shot.entity)
+                .and_then(|player| state.get_active_weapon(player))
+            else {
+                continue;
+            };
+            let Some(kind) = crit_kind(&weapon.class_name).filter(|k| *k != CritKind::Melee) else {
+                continue;
+            };
+            let Some((damage, _)) = attack_damage(weapon) else {
+                continue;
+            };
+            let bucket = self
+                .buckets
+                .entry(weapon.entity)
+                .or_insert_with(|| CritBucket::new(weapon.item_definition_index));
+            // Hitscan attacks come with both bullets and an animation
+            if bucket.last_attack == tick {
+                continue;
+            }
+            bucket.last_attack = tick;
+            bucket.tokens = (bucket.tokens + damage).min(CRIT_BUCKET_CAP);
+            if kind != CritKind::Rapid || bucket.last_roll.is_none_or(|t| tick >= t + roll_interval) {
+                bucket.checks += 1;
+                bucket.last_roll = Some(tick);
+            }
+        }
+    }
+
+    // Share of the player's ranged damage this round that was random crits, normalised by the crit
+    // multiplier (CTFWeaponBase::CalcIsAttackCriticalHelper)
+    fn observed_crit_chance(&self, steam_id: u64) -> f32 {
+        let Some((crit, total)) = self.ranged_damage.get(&steam_id) else {
+            return 0.0;
+        };
+        let normalized = crit / CRIT_DAMAGE_MULT;
+        let non_crit = total - crit;
+        if normalized + non_crit <= 0.0 {
+            return 0.0;
+        }
+        normalized / (normalized + non_crit)
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for CritHack {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "crit_hack"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        self.buckets.retain(|id, bucket| {
+            state
+                .weapons
+                .get(id)
+                .is_some_and(|w| w.item_definition_index == bucket.item_definition_index)
+        });
+        for (id, bucket) in self.buckets.iter_mut() {
+            // Attacks out of view aren't networked, so the bucket could have filled up since
+            let seen = state
+                .weapons
+                .get(id)
+                .and_then(|w| state.get_player_by_entid(w.owner))
+                .is_some_and(|p| p.in_pvs);
+            if !seen {
+                bucket.tokens = CRIT_BUCKET_CAP;
+            }
+        }
+        Ok(vec![])
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![
+            tf_demo_parser::MessageType::GameEvent,
+            tf_demo_parser::MessageType::TempEntities,
+        ])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        parser_state: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let event_msg = match message {
+            Message::GameEvent(event_msg) => event_msg,
+            Message::TempEntities(msg) => {
+                self.fill_buckets(msg, state, parser_state, u32::from(tick));
+                return Ok(vec![]);
+            }
+            _ => return Ok(vec![]),
+        };
+        let hurt = match &event_msg.event {
+            GameEvent::PlayerHurt(hurt) => hurt,
+            GameEvent::TeamPlayRoundStart(_) => {
+                self.ranged_damage.clear();
+                return Ok(vec![]);
+            }
+            _ => return Ok(vec![]),
+        };
+        if hurt.attacker == 0 || hurt.attacker == hurt.user_id || is_sentry_damage(hurt.weapon_id) {
+            return Ok(vec![]);
+        }
+        let tick = u32::from(tick);
+
+        let find_player = |user_id: u16| {
+            state.players.iter().find(|p| {
+                p.info
+                    .as_ref()
+                    .is_some_and(|info| u16::from(info.user_id) == user_id)
+            })
+        };
+        let (Some(attacker), Some(victim)) = (find_player(hurt.attacker), find_player(hurt.user_id))
+        else {
+            return Ok(vec![]);
+        };
+        let Some(info) = &attacker.info else {
+            return Ok(vec![]);
+        };
+        if info.steam_id == "BOT" {
+            return Ok(vec![]);
+        }
+        let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+
+        // The chance of this hit depends on the damage dealt before it
+        let crit_mult = self.crit_mult(steam_id, tick);
+        if let Some(damage) = self.recent_damage.get_mut(&steam_id) {
+            damage.push_back((tick, hurt.damage_amount));
+        }
+
+        // Damage from a weapon the attacker has since switched away from isn't a hit of the one they hold
+        let held = state
+            .get_active_weapon(attacker)
+            .filter(|w| weapon_id(&w.class_name) == Some(hurt.weapon_id));
+        let melee = held.is_some_and(|w| {
+            w.class_name == "CTFKnife" || crit_kind(&w.class_name) == Some(CritKind::Melee)
+        });
+        // The cap is checked against the observed rate before this hit, which counts all ranged damage
+        let observed_crit_chance = self.observed_crit_chance(steam_id);
+        if !melee {
+            self.ranged_damage.entry(steam_id).or_default().1 += hurt.damage_amount as f32;
+        }
+
+        let Some(weapon) = held else {
+            return Ok(vec![]);
+        };
+        let Some(kind) = crit_kind(&weapon.class_name) else {
+            return Ok(vec![]);
+        };
+        let item_mult = match get_item(weapon.item_definition_index) {
+            Some(item) if item.has_conditional_crits() => return Ok(vec![]),
+            Some(item) => item.random_crit_multiplier(),
+            None => return Ok(vec![]),
+        };
+        if item_mult <= 0.0 {
+            return Ok(vec![]);
+        }
+
+        let base = if kind == CritKind::Melee { CRIT_CHANCE_MELEE } else { CRIT_CHANCE };
+        let chance = (base * crit_mult * item_mult).min(1.0);
+        let capped =
+            kind != CritKind::Melee && observed_crit_chance > base * crit_mult + CRIT_CAP_MARGIN;
+
+        if hurt.mini_crit
+            || crit_boosted(attacker)
+            || mini_crit_boosted(attacker)
+            || victim_excluded(victim)
+            || matches!(
+                hurt.custom,
+                DMG_CUSTOM_HEADSHOT
+                    | DMG_CUSTOM_BACKSTAB
+                    | DMG_CUSTOM_BURNING
+                    | DMG_CUSTOM_BURNING_FLARE
+                    | DMG_CUSTOM_BURNING_ARROW
+                    | DMG_CUSTOM_SHOTGUN_REVENGE_CRIT
+                    | DMG_CUSTOM_BLEEDING
+                    | DMG_CUSTOM_HEADSHOT_DECAPITATION
+            )
+        {
+            return Ok(vec![]);
+        }
+        if kind != CritKind::Melee && hurt.crit {
+            self.ranged_damage.entry(steam_id).or_default().0 += hurt.damage_amount as f32;
+        }
+
+        let stats = self
+            .stats
+            .entry((steam_id, weapon.item_definition_index))
+            .or_insert_with(|| WeaponStats {
+                item_definition_index: weapon.item_definition_index,
+                kind,
+                trials: Vec::new(),
+                crit_ticks: Vec::new(),
+                capped_crit_ticks: Vec::new(),
+                overdrawn_crit_ticks: Vec::new(),
+                stream_until: 0,
+            });
+
+        // Over the cap the server doesn't let the weapon crit at all
+        if capped {
+            if hurt.crit {
+                stats.capped_crit_ticks.push(tick);
+            }
+            return Ok(vec![]);
+        }
+
+        let (slot, chance) = if kind == CritKind::Rapid {
+            // Hits during a crit stream don't roll
+            if tick < stats.stream_until {
+                return Ok(vec![]);
+            }
+            // The chance per roll is set so that the time spent in crit streams matches the crit chance
+            let roll_chance = if chance >= 1.0 {
+                1.0
+            } else {
+                (chance / (CRIT_DURATION_RAPID * (1.0 - chance))).min(1.0)
+            };
+            (tick / (CRIT_ROLL_INTERVAL_RAPID / TICK_INTERVAL) as u32, roll_chance)
+        } else {
+            (tick, chance)
+        };
+
+        // A trial is paid for once, when its first crit lands
+        let same_trial = stats.trials.last().is_some_and(|t| t.slot == slot);
+        let new_crit = hurt.crit && !(same_trial && stats.trials.last().is_some_and(|t| t.crit));
+        if let Some(damage) = crit_damage(weapon, kind).filter(|_| kind != CritKind::Melee) {
+            let bucket = self
+                .buckets
+                .entry(attacker.active_weapon)
+                .or_insert_with(|| CritBucket::new(weapon.item_definition_index));
+            if new_crit && !bucket.withdraw(damage) {
+                stats.overdrawn_crit_ticks.push(tick);
+                return Ok(vec![]);
+            }
+            // The bucket couldn't have paid for a crit, so the hit never had a chance to be one
+            if !hurt.crit && !same_trial && bucket.cost(damage) > bucket.tokens {
+                return Ok(vec![]);
+            }
+        }
+
+        if hurt.crit {
+            if kind == CritKind::Rapid {
+                stats.stream_until = tick + (CRIT_DURATION_RAPID / TICK_INTERVAL) as u32;
+            }
+            stats.crit_ticks.push(tick);
+        }
+        // Hitting several players with one shot (or in one roll) is a single trial
+        match stats.trials.last_mut() {
+            Some(last) if last.slot == slot => last.crit |= hurt.crit,
+            _ => stats.trials.push(Trial {
+                slot,
+                chance: chance as f64,
+                crit: hurt.crit,
+            }),
+        }
+
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_trials: i32 = get_parameter_value(&self.params, "min_trials");
+        let max_p_value: f32 = get_parameter_value(&self.params, "max_p_value");
+        let min_capped_crits: i32 = get_parameter_value(&self.params, "min_capped_crits");
+        let min_overdrawn_crits: i32 = get_parameter_value(&self.params, "min_overdrawn_crits");
+
+        let mut players: HashMap<u64, Vec<&WeaponStats>> = HashMap::new();
+        for ((steam_id, _), stats) in &self.stats {
+            players.entry(*steam_id).or_default().push(stats);
+        }
+
+        let mut detections = Vec::new();
+        for (steam_id, weapons) in players {
+            let probabilities: Vec<f64> = weapons
+                .iter()
+                .flat_map(|w| w.trials.iter().map(|t| t.chance))
+                .collect();
+            let crits: usize = weapons.iter().map(|w| w.crits()).sum();
+            let capped_crits: usize = weapons.iter().map(|w| w.capped_crit_ticks.len()).sum();
+            let overdrawn_crits: usize =
+                weapons.iter().map(|w| w.overdrawn_crit_ticks.len()).sum();
+            let p_value = upper_tail(&probabilities, crits);
+            let improbable =
+                probabilities.len() as i32 >= min_trials && p_value <= max_p_value as f64;
+            if !improbable
+                && (capped_crits as i32) < min_capped_crits
+                && (overdrawn_crits as i32) < min_overdrawn_crits
+            {
+                continue;
+            }
+
+            let expected: f64 = probabilities.iter().sum();
+            let weapon_data: Vec<_> = weapons
+                .iter()
+                .map(|w| {
+                    let trials = w.trials.len().max(1) as f64;
+                    let crit_rate = w.crits() as f64 / trials;
+                    let expected_rate = w.expected() / trials;
+                    let chances: Vec<f64> = w.trials.iter().map(|t| t.chance).collect();
+                    json!({
+                        "weapon": item_json(w.item_definition_index),
+                        "rapid_fire": w.kind == CritKind::Rapid,
+                        "melee": w.kind == CritKind::Melee,
+                        "trials": w.trials.len(),
+                        "crits": w.crits(),
+                        "crit_rate": crit_rate,
+                        "expected_crit_rate": expected_rate,
+                        "p_value": upper_tail(&chances, w.crits()),
+                        "crit_ticks": w.crit_ticks,
+                        "crits_over_cap_ticks": w.capped_crit_ticks,
+                        "crits_over_bucket_ticks": w.overdrawn_crit_ticks,
+                    })
+                })
+                .collect();
+            let first_crit = weapons
+                .iter()
+                .flat_map(|w| {
+                    w.crit_ticks
+                        .first()
+                        .into_iter()
+                        .chain(w.capped_crit_ticks.first())
+                        .chain(w.overdrawn_crit_ticks.first())
+                })
+                .min()
+                .copied()
+                .unwrap_or_default();
+
+            detections.push(Detection {
+                tick: first_crit,
+                algorithm: self.algorithm_name().to_string(),
+                player: steam_id,
+                data: json!({
+                    "trials": probabilities.len(),
+                    "crits": crits,
+                    "expected_crits": expected,
+                    "p_value": p_value,
+                    "crits_over_cap": capped_crits,
+                    "crits_over_bucket": overdrawn_crits,
+                    "weapons": weapon_data,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
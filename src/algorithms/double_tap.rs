@@ -5,6 +5,7 @@ use crate::base::cheat_analyser_base::CheatAnalyserState;
 
 use crate::lib::algorithm::{CheatAlgorithm, Detection};
 use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::items_game::item_json;
 
 use anyhow::Error;
 use serde_json::{Map, Value};
@@ -154,6 +155,9 @@ impl<'a> CheatAlgorithm<'a> for DoubleTap {
                 let diff = ticknum - past_tick;
 
                 if diff < min_diff && diff > 0 {
+                    let weapon_item = state
+                        .get_active_weapon(attacker)
+                        .map_or(Value::Null, |w| item_json(w.item_definition_index));
                     let u200b = "​";
                     let data: Vec<(&str, Value)> = vec![
                         ("class", Value::from(attacker.class.to_string())),
@@ -164,6 +168,7 @@ impl<'a> CheatAlgorithm<'a> for DoubleTap {
                         ("weapon_id", Value::from(weapon)),
                         ("damage_1", Value::from(past_dmg)),
                         ("damage_2", Value::from(dmg)),
+                        ("weapon", weapon_item),
                     ];
                     let mut new_data = Map::new();
                     for (i, (key, value)) in data.into_iter().enumerate() {
@@ -0,0 +1,213 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+
+use anyhow::Error;
+use serde_json::json;
+use steamid_ng::SteamID;
+use tf_demo_parser::ParserState;
+
+// Fake lag chokes usercmds on purpose so the player is only simulated (and their origin only sent)
+// every N ticks, while everyone else is updated every snapshot. Each time a player's simulation tick
+// advances, the server ticks since their previous update are compared to the snapshot interval.
+// A sustained choke while the scoreboard ping looks normal is flagged as one detection per period.
+
+struct ChokePeriod {
+    start_tick: u32,
+    end_tick: u32,
+    // Server ticks between updates, beyond the snapshot interval
+    chokes: Vec<u32>,
+    // Demo ticks between origin updates
+    origin_intervals: Vec<u32>,
+    pings: Vec<u16>,
+}
+
+#[derive(Default)]
+struct Cadence {
+    sim_tick: u32,
+    last_update: u32,
+    origin_update_tick: u32,
+    window: VecDeque<u32>,
+    period: Option<ChokePeriod>,
+}
+
+#[derive(Default)]
+pub struct FakeLag {
+    params: Parameters,
+
+    cadences: HashMap<u64, Cadence>,
+    // Server tick of the entity data currently in the state (see Tickbase)
+    data_server_tick: u32,
+    last_tick: u32,
+}
+
+impl FakeLag {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("window_updates".to_string(), Parameter::Int(20)),
+                ("min_choke_ticks".to_string(), Parameter::Int(8)),
+                ("min_duration_ticks".to_string(), Parameter::Int(660)),
+                ("max_ping".to_string(), Parameter::Int(150)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn end_period(&self, steam_id: u64, period: ChokePeriod) -> Option<Detection> {
+        let min_duration_ticks: i32 = get_parameter_value(&self.params, "min_duration_ticks");
+        let duration = period.end_tick - period.start_tick;
+        if (duration as i32) < min_duration_ticks {
+            return None;
+        }
+
+        let mut histogram: BTreeMap<u32, u32> = BTreeMap::new();
+        for choke in &period.chokes {
+            *histogram.entry(*choke).or_default() += 1;
+        }
+        let mean = |values: &[u32]| values.iter().sum::<u32>() as f64 / values.len().max(1) as f64;
+
+        Some(Detection {
+            tick: period.start_tick,
+            algorithm: self.algorithm_name().to_string(),
+            player: steam_id,
+            data: json!({
+                "start_tick": period.start_tick,
+                "end_tick": period.end_tick,
+                "duration_ticks": duration,
+                "updates": period.chokes.len(),
+                "mean_choke_ticks": mean(&period.chokes),
+                "max_choke_ticks": period.chokes.iter().max(),
+                "choke_histogram": histogram,
+                "mean_origin_interval_ticks": mean(&period.origin_intervals),
+                "min_ping": period.pings.iter().min(),
+                "max_ping": period.pings.iter().max(),
+            }),
+        })
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for FakeLag {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "fake_lag"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let server_tick = self.data_server_tick;
+        let snapshot_ticks = u32::from(state.server_tick).saturating_sub(server_tick);
+        self.data_server_tick = u32::from(state.server_tick);
+        self.last_tick = u32::from(state.tick);
+        if server_tick == 0 {
+            return Ok(vec![]);
+        }
+
+        let window_updates: i32 = get_parameter_value(&self.params, "window_updates");
+        let min_choke_ticks: i32 = get_parameter_value(&self.params, "min_choke_ticks");
+        let max_ping: i32 = get_parameter_value(&self.params, "max_ping");
+
+        let mut detections = Vec::new();
+        let mut tracked = Vec::new();
+
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            if !player.in_pvs
+                || player.state != PlayerState::Alive
+                || info.steam_id == "BOT"
+                || player.simulation_tick == 0
+            {
+                continue;
+            }
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            tracked.push(steam_id);
+
+            let cadence = self.cadences.entry(steam_id).or_default();
+            if cadence.sim_tick == 0 {
+                cadence.sim_tick = player.simulation_tick;
+                cadence.last_update = server_tick;
+                cadence.origin_update_tick = u32::from(player.origin_update_tick);
+                continue;
+            }
+            if player.simulation_tick <= cadence.sim_tick {
+                continue;
+            }
+
+            let interval = server_tick.saturating_sub(cadence.last_update);
+            // The snapshot interval is the best any player can be seen updating
+            let choke = interval.saturating_sub(snapshot_ticks.max(1));
+            cadence.sim_tick = player.simulation_tick;
+            cadence.last_update = server_tick;
+
+            let origin_update_tick = u32::from(player.origin_update_tick);
+            let origin_interval = origin_update_tick.saturating_sub(cadence.origin_update_tick);
+            cadence.origin_update_tick = origin_update_tick;
+
+            cadence.window.push_back(choke);
+            if cadence.window.len() > window_updates.max(1) as usize {
+                cadence.window.pop_front();
+            }
+            if cadence.window.len() < window_updates.max(1) as usize {
+                continue;
+            }
+
+            let mean_choke =
+                cadence.window.iter().sum::<u32>() as f32 / cadence.window.len() as f32;
+            let choking = mean_choke >= min_choke_ticks as f32 && (player.ping as i32) <= max_ping;
+
+            if choking {
+                let period = cadence.period.get_or_insert_with(|| ChokePeriod {
+                    start_tick: self.last_tick,
+                    end_tick: self.last_tick,
+                    chokes: Vec::new(),
+                    origin_intervals: Vec::new(),
+                    pings: Vec::new(),
+                });
+                period.end_tick = self.last_tick;
+                period.chokes.push(choke);
+                if origin_interval > 0 {
+                    period.origin_intervals.push(origin_interval);
+                }
+                period.pings.push(player.ping);
+            } else if let Some(period) = cadence.period.take() {
+                detections.extend(self.end_period(steam_id, period));
+            }
+        }
+
+        // Dead or out of PVS players aren't updated, so their cadence starts over
+        let stale: Vec<u64> = self
+            .cadences
+            .keys()
+            .filter(|steam_id| !tracked.contains(steam_id))
+            .copied()
+            .collect();
+        for steam_id in stale {
+            if let Some(period) = self.cadences.remove(&steam_id).and_then(|c| c.period) {
+                detections.extend(self.end_period(steam_id, period));
+            }
+        }
+
+        Ok(detections)
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let mut detections = Vec::new();
+        for (steam_id, cadence) in std::mem::take(&mut self.cadences) {
+            if let Some(period) = cadence.period {
+                detections.extend(self.end_period(steam_id, period));
+            }
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, Player, PlayerCondition, Weapon};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{is_sentry_damage, length, weapon_id};
+use crate::util::items_game::{get_item, item_json};
+use crate::util::shots::{shots_fired, ShotSource};
+
+use anyhow::Error;
+use serde_json::json;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::gameevent_gen::GameEvent;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::ParserState;
+
+// Flags any weapon being fired again faster than its refire delay allows (doubletap, warp, rapid fire).
+// Shots are taken from bullet temp entities, newly spawned projectiles seen next to their shooter and hurt
+// events. Hurt events only count when they came from the weapon the attacker is holding, and never for
+// sentries or projectiles.
+
+const TICK_INTERVAL: f32 = 0.015;
+
+// Snapshot gaps larger than this are treated as a pause rather than the demo's snapshot rate.
+const MAX_SNAPSHOT_INTERVAL: u32 = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+enum WeaponKind {
+    Hitscan,
+    Projectile,
+    Melee,
+}
+
+// Base refire delay (TimeFireDelay in the weapon scripts) by weapon server class.
+// Weapons without a fixed refire delay (flamethrowers, bows, mediguns, ...) are not checked.
+fn base_fire_delay(class_name: &str) -> Option<(f32, WeaponKind)> {
+    use WeaponKind::*;
+    Some(match class_name {
+        "CTFScatterGun" | "CTFSodaPopper" | "CTFPEPBrawlerBlaster" | "CTFShotgun"
+        | "CTFShotgun_Soldier" | "CTFShotgun_HWG" | "CTFShotgun_Pyro" | "CTFShotgun_Revenge" => {
+            (0.625, Hitscan)
+        }
+        "CTFPistol" | "CTFPistol_Scout" | "CTFPistol_ScoutSecondary" => (0.15, Hitscan),
+        "CTFPistol_ScoutPrimary" => (0.36, Hitscan),
+        "CTFMinigun" | "CTFSMG" | "CTFChargedSMG" => (0.1, Hitscan),
+        "CTFSniperRifle" | "CTFSniperRifleDecap" | "CTFSniperRifleClassic" => (1.5, Hitscan),
+        "CTFRevolver" => (0.5, Hitscan),
+        "CTFRocketLauncher" | "CTFRocketLauncher_DirectHit" | "CTFRocketLauncher_AirStrike"
+        | "CTFParticleCannon" | "CTFRaygun" | "CTFDRGPomson" | "CTFFlareGun_Revenge" => {
+            (0.8, Projectile)
+        }
+        "CTFGrenadeLauncher" | "CTFCannon" | "CTFPipebombLauncher" => (0.6, Projectile),
+        "CTFShotgunBuildingRescue" => (0.625, Projectile),
+        "CTFSyringeGun" => (0.1, Projectile),
+        "CTFCrossbow" => (0.24, Projectile),
+        "CTFFlareGun" => (2.0, Projectile),
+        "CTFBat" | "CTFBat_Wood" | "CTFBat_Fish" | "CTFBat_Giftwrap" => (0.5, Melee),
+        "CTFShovel" | "CTFBottle" | "CTFStickBomb" | "CTFSword" | "CTFKatana" | "CTFFireAxe"
+        | "CTFFists" | "CTFBonesaw" | "CTFWrench" | "CTFRobotArm" | "CTFKnife" | "CTFClub"
+        | "CTFBreakableMelee" | "CTFBreakableSign" | "CTFSlap" => (0.8, Melee),
+        _ => return None,
+    })
+}
+
+// Damage sources in player_hurt that come from the shot itself rather than afterburn, bleed, taunts etc.
+// (none, headshot, backstab, minigun, penetration, penetration headshot)
+fn is_direct_damage(custom: u16) -> bool {
+    matches!(custom, 0 | 1 | 2 | 5 | 11 | 12 | 14)
+}
+
+#[derive(Default)]
+pub struct FireRate {
+    params: Parameters,
+
+    // Last shot per (player, weapon entity): (tick, source)
+    last_shots: HashMap<(u64, EntityId), (u32, &'static str)>,
+    seen_projectiles: HashMap<EntityId, DemoTick>,
+    last_tick: u32,
+    snapshot_interval: u32,
+}
+
+impl FireRate {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("tolerance_ticks".to_string(), Parameter::Int(1)),
+                // Furthest from the shooter's eyes a newly seen projectile can be and still count as just
+                // fired. Projectiles fired out of view are first seen wherever they fly into view.
+                ("max_spawn_distance".to_string(), Parameter::Float(300.0)),
+                // Fire rate multiplier to assume when the weapon can't be looked up in items_game.txt.
+                // 0.5 is the strongest fire rate bonus on any weapon, so this never flags legit items.
+                ("unknown_item_multiplier".to_string(), Parameter::Float(0.5)),
+            ]),
+            snapshot_interval: 1,
+            ..Default::default()
+        }
+    }
+
+    // Fastest legal refire multiplier for this weapon in the player's current state.
+    fn fire_rate_multiplier(&self, weapon: &Weapon, player: &Player) -> f32 {
+        let mut multiplier = match get_item(weapon.item_definition_index) {
+            Some(item) => item
+                .fire_rate_attributes()
+                .map(|attr| match attr.attribute_class.as_str() {
+                    "mult_postfiredelay" => attr.value,
+                    // Scales with missing health, assume the best case
+                    "mult_postfiredelay_with_reduced_health" => attr.value.min(1.0),
+                    "rocketjump_attackrate_bonus"
+                        if player.has_condition(PlayerCondition::BlastJumping) =>
+                    {
+                        attr.value
+                    }
+                    _ => 1.0,
+                })
+                .product(),
+            None => get_parameter_value(&self.params, "unknown_item_multiplier"),
+        };
+        if player.has_condition(PlayerCondition::RuneHaste) {
+            multiplier *= 0.5;
+        }
+        if player.has_condition(PlayerCondition::KingBuffed) {
+            multiplier *= 0.75;
+        }
+        multiplier
+    }
+
+    fn register_shot(
+        &mut self,
+        state: &CheatAnalyserState,
+        player_entity: EntityId,
+        weapon_entity: EntityId,
+        tick: u32,
+        source: &'static str,
+    ) -> Option<Detection> {
+        let weapon = state.weapons.get(&weapon_entity)?;
+        let (base_delay, kind) = base_fire_delay(&weapon.class_name)?;
+        // Projectile hurt events happen on impact, not when the weapon was fired
+        if source == "hurt" && kind == WeaponKind::Projectile {
+            return None;
+        }
+        let steam_id = state.get_id64_from_entid(player_entity)?;
+        let player = state.get_player_by_entid(player_entity)?;
+
+        let (prev_tick, prev_source) = self
+            .last_shots
+            .insert((steam_id, weapon_entity), (tick, source))?;
+        // Several pellets, victims or sources for the same shot
+        if tick <= prev_tick {
+            self.last_shots
+                .insert((steam_id, weapon_entity), (prev_tick, prev_source));
+            return None;
+        }
+
+        let tolerance_ticks: i32 = get_parameter_value(&self.params, "tolerance_ticks");
+
+        let multiplier = self.fire_rate_multiplier(weapon, player);
+        let min_interval = (base_delay * multiplier / TICK_INTERVAL + 0.001).floor() as u32;
+        // Events are only recorded once per snapshot, so intervals can appear shorter by up to one snapshot
+        let allowance = tolerance_ticks.max(0) as u32 + self.snapshot_interval.saturating_sub(1);
+        let interval = tick - prev_tick;
+
+        if interval + allowance >= min_interval {
+            return None;
+        }
+
+        Some(Detection {
+            tick,
+            algorithm: self.algorithm_name().to_string(),
+            player: steam_id,
+            data: json!({
+                "class": player.class.to_string(),
+                "weapon": item_json(weapon.item_definition_index),
+                "weapon_class": weapon.class_name,
+                "tick_1": prev_tick,
+                "tick_2": tick,
+                "source_1": prev_source,
+                "source_2": source,
+                "interval_ticks": interval,
+                "min_interval_ticks": min_interval,
+                "fire_rate_multiplier": multiplier,
+                "snapshot_interval": self.snapshot_interval,
+            }),
+        })
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for FireRate {
+    fn default(&self) -> bool {
+        true
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "fire_rate"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let max_spawn_distance: f32 = get_parameter_value(&self.params, "max_spawn_distance");
+        let ticknum = u32::from(state.tick);
+        if self.last_tick > 0 && ticknum > self.last_tick {
+            self.snapshot_interval = (ticknum - self.last_tick).min(MAX_SNAPSHOT_INTERVAL);
+        }
+        self.last_tick = ticknum;
+
+        let mut detections = Vec::new();
+
+        self.seen_projectiles
+            .retain(|id, _| state.projectiles.contains_key(id));
+
+        for (id, projectile) in &state.projectiles {
+            if self.seen_projectiles.insert(*id, projectile.spawn_tick) == Some(projectile.spawn_tick) {
+                continue;
+            }
+            // Deflected projectiles already existed before their current owner touched them
+            if projectile.deflected > 0 {
+                continue;
+            }
+            let Some(weapon) = state.weapons.get(&projectile.launcher) else {
+                continue;
+            };
+            let near_owner = state.get_player_by_entid(weapon.owner).is_some_and(|owner| {
+                owner.in_pvs && length(projectile.position - owner.eye_position()) <= max_spawn_distance
+            });
+            if !near_owner {
+                continue;
+            }
+            detections.extend(self.register_shot(
+                state,
+                weapon.owner,
+                projectile.launcher,
+                projectile.spawn_tick.into(),
+                "projectile",
+            ));
+        }
+
+        Ok(detections)
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![
+            tf_demo_parser::MessageType::GameEvent,
+            tf_demo_parser::MessageType::TempEntities,
+        ])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        parser_state: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let mut detections = Vec::new();
+        let ticknum = u32::from(tick);
+
+        match message {
+            Message::TempEntities(msg) => {
+                for shot in shots_fired(msg, parser_state) {
+                    if !matches!(shot.source, ShotSource::Bullets { .. }) {
+                        continue;
+                    }
+                    let Some(player) = state.get_player_by_entid(shot.entity) else {
+                        continue;
+                    };
+                    detections.extend(self.register_shot(
+                        state,
+                        shot.entity,
+                        player.active_weapon,
+                        ticknum,
+                        "bullets",
+                    ));
+                }
+            }
+            Message::GameEvent(event_msg) => {
+                if let GameEvent::PlayerHurt(hurt) = &event_msg.event {
+                    if hurt.attacker == hurt.user_id
+                        || !is_direct_damage(hurt.custom)
+                        || is_sentry_damage(hurt.weapon_id)
+                    {
+                        return Ok(vec![]);
+                    }
+                    let Some(attacker) = state.players.iter().find(|p| {
+                        p.info
+                            .as_ref()
+                            .is_some_and(|info| u32::from(info.user_id) == u32::from(hurt.attacker))
+                    }) else {
+                        return Ok(vec![]);
+                    };
+                    // Damage from a weapon the attacker has since switched away from isn't a shot of the
+                    // one they are holding
+                    let same_weapon = state
+                        .get_active_weapon(attacker)
+                        .and_then(|weapon| weapon_id(&weapon.class_name))
+                        .is_some_and(|id| id == hurt.weapon_id);
+                    if !same_weapon {
+                        return Ok(vec![]);
+                    }
+                    detections.extend(self.register_shot(
+                        state,
+                        attacker.entity_id(),
+                        attacker.active_weapon,
+                        ticknum,
+                        "hurt",
+                    ));
+                }
+            }
+            _ => {}
+        }
+
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, Player, PlayerCondition, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+
+use anyhow::Error;
+use serde_json::json;
+use steamid_ng::SteamID;
+use tf_demo_parser::ParserState;
+
+// Flags players outside the world's bounding box, and players that stay in the air without falling
+// (noclip/fly). Airborne stretches are judged by the vertical acceleration fitted over the whole stretch:
+// anything in free fall accelerates downwards at sv_gravity, flying doesn't.
+
+const TICK_INTERVAL: f32 = 0.015;
+const GRAVITY: f32 = 800.0;
+// Points kept in the position trace of a detection
+const MAX_TRACE_POINTS: usize = 64;
+
+// Ways to stay in the air without gravity pulling the player down
+fn ignores_gravity(player: &Player) -> bool {
+    use PlayerCondition::*;
+    player.in_water()
+        || [
+            BlastJumping,
+            KnockedIntoAir,
+            GrapplingHook,
+            GrapplingHookLatched,
+            GrappledToPlayer,
+            GrappledByPlayer,
+            ParachuteActive,
+            ParachuteDeployed,
+            AirCurrent,
+            HalloweenGhostMode,
+            HalloweenKart,
+            SwimmingCurse,
+        ]
+        .into_iter()
+        .any(|cond| player.has_condition(cond))
+}
+
+// (tick, simulation tick, position, vertical velocity)
+type TracePoint = (u32, u32, [f32; 3], f32);
+
+fn trace_json(trace: &[TracePoint]) -> Vec<[f32; 4]> {
+    let step = trace.len().div_ceil(MAX_TRACE_POINTS).max(1);
+    trace
+        .iter()
+        .step_by(step)
+        .map(|(tick, _, pos, _)| [*tick as f32, pos[0], pos[1], pos[2]])
+        .collect()
+}
+
+#[derive(Default)]
+struct WorldTracker {
+    sim_tick: u32,
+    outside: Vec<TracePoint>,
+    max_outside_distance: f32,
+    airborne: Vec<TracePoint>,
+}
+
+#[derive(Default)]
+pub struct OutOfWorld {
+    params: Parameters,
+
+    trackers: HashMap<u64, WorldTracker>,
+}
+
+impl OutOfWorld {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("world_margin".to_string(), Parameter::Float(16.0)),
+                ("min_air_ticks".to_string(), Parameter::Int(132)),
+                // Flag airborne stretches falling slower than this fraction of gravity
+                ("max_gravity_fraction".to_string(), Parameter::Float(0.5)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn end_outside(&self, steam_id: u64, tracker: &mut WorldTracker) -> Option<Detection> {
+        let trace = std::mem::take(&mut tracker.outside);
+        let max_outside_distance = std::mem::take(&mut tracker.max_outside_distance);
+        let (first, last) = (trace.first()?, trace.last()?);
+        Some(Detection {
+            tick: first.0,
+            algorithm: self.algorithm_name().to_string(),
+            player: steam_id,
+            data: json!({
+                "type": "out_of_world",
+                "start_tick": first.0,
+                "end_tick": last.0,
+                "max_outside_distance": max_outside_distance,
+                "trace": trace_json(&trace),
+            }),
+        })
+    }
+
+    fn end_airborne(&self, steam_id: u64, tracker: &mut WorldTracker) -> Option<Detection> {
+        let trace = std::mem::take(&mut tracker.airborne);
+        let min_air_ticks: i32 = get_parameter_value(&self.params, "min_air_ticks");
+        let max_gravity_fraction: f32 = get_parameter_value(&self.params, "max_gravity_fraction");
+
+        let (first, last) = (trace.first()?, trace.last()?);
+        let air_ticks = last.1 - first.1;
+        if (air_ticks as i32) < min_air_ticks {
+            return None;
+        }
+
+        // Least squares slope of vertical velocity over time
+        let n = trace.len() as f32;
+        let times: Vec<f32> = trace
+            .iter()
+            .map(|p| (p.1 - first.1) as f32 * TICK_INTERVAL)
+            .collect();
+        let mean_t = times.iter().sum::<f32>() / n;
+        let mean_v = trace.iter().map(|p| p.3).sum::<f32>() / n;
+        let (cov, var) = trace
+            .iter()
+            .zip(&times)
+            .fold((0.0, 0.0), |(cov, var), (p, t)| {
+                (cov + (t - mean_t) * (p.3 - mean_v), var + (t - mean_t).powi(2))
+            });
+        if var <= 0.0 {
+            return None;
+        }
+        let acceleration = cov / var;
+        if acceleration < -GRAVITY * max_gravity_fraction {
+            return None;
+        }
+
+        Some(Detection {
+            tick: first.0,
+            algorithm: self.algorithm_name().to_string(),
+            player: steam_id,
+            data: json!({
+                "type": "fly",
+                "start_tick": first.0,
+                "end_tick": last.0,
+                "air_ticks": air_ticks,
+                "vertical_acceleration": acceleration,
+                "trace": trace_json(&trace),
+            }),
+        })
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for OutOfWorld {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "out_of_world"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let world_margin: f32 = get_parameter_value(&self.params, "world_margin");
+        let ticknum = u32::from(state.tick);
+
+        let mut detections = Vec::new();
+        let mut trackers = std::mem::take(&mut self.trackers);
+
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            let tracked =
+                player.in_pvs && player.state == PlayerState::Alive && info.steam_id != "BOT";
+            if !tracked {
+                if let Some(tracker) = trackers.get_mut(&steam_id) {
+                    detections.extend(self.end_outside(steam_id, tracker));
+                    detections.extend(self.end_airborne(steam_id, tracker));
+                    tracker.sim_tick = 0;
+                }
+                continue;
+            }
+
+            let tracker = trackers.entry(steam_id).or_default();
+            if player.simulation_tick <= tracker.sim_tick {
+                continue;
+            }
+            tracker.sim_tick = player.simulation_tick;
+
+            let pos = player.position;
+            let point = (ticknum, player.simulation_tick, [pos.x, pos.y, pos.z], player.velocity.z);
+
+            if let Some(world) = &state.world {
+                let (min, max) = (world.boundary_min, world.boundary_max);
+                let outside = [
+                    min.x - world_margin - pos.x,
+                    pos.x - max.x - world_margin,
+                    min.y - world_margin - pos.y,
+                    pos.y - max.y - world_margin,
+                    min.z - world_margin - pos.z,
+                    pos.z - max.z - world_margin,
+                ]
+                .into_iter()
+                .fold(0.0f32, f32::max);
+                if outside > 0.0 {
+                    tracker.outside.push(point);
+                    tracker.max_outside_distance = tracker.max_outside_distance.max(outside);
+                } else {
+                    detections.extend(self.end_outside(steam_id, tracker));
+                }
+            }
+
+            if player.on_ground() || ignores_gravity(player) {
+                detections.extend(self.end_airborne(steam_id, tracker));
+            } else {
+                tracker.airborne.push(point);
+            }
+        }
+
+        self.trackers = trackers;
+        Ok(detections)
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let mut trackers = std::mem::take(&mut self.trackers);
+        let mut detections = Vec::new();
+        for (steam_id, tracker) in trackers.iter_mut() {
+            detections.extend(self.end_outside(*steam_id, tracker));
+            detections.extend(self.end_airborne(*steam_id, tracker));
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerState, ProjectileType};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{angle_vectors, dot, length, vector_angle};
+use crate::util::items_game::item_json;
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::vector::Vector;
+use tf_demo_parser::ParserState;
+
+// Compares the launch direction of each new projectile with where its owner was looking.
+// Rockets, flares and arrows are fired from an offset (right hand, or centred for the Original) towards
+// the point under the crosshair, so their path has to cross the eye ray; the offset itself doesn't matter.
+// Pipes and stickies are launched along the eye's forward vector plus 200 u/s along its up vector.
+// Eye angles are checked from the snapshots before and after the projectile appears, and only a
+// mismatch with both counts. Players with repeated mismatches are flagged once at the end.
+
+// Launch speed added along the eye's up vector for grenades
+const GRENADE_UP_SPEED: f32 = 200.0;
+
+#[derive(Clone, Copy)]
+struct Eye {
+    position: Vector,
+    yaw: f32,
+    pitch: f32,
+}
+
+// Shortest distance between the eye ray and the projectile's line of flight
+fn miss_distance(eye: &Eye, origin: Vector, velocity: Vector) -> f32 {
+    let (d, _, _) = angle_vectors(eye.yaw, eye.pitch);
+    let u = velocity * (1.0 / length(velocity));
+    let w0 = eye.position - origin;
+    let (b, dd, e) = (dot(d, u), dot(d, w0), dot(u, w0));
+    let denom = 1.0 - b * b;
+    let (mut s, mut t) = if denom.abs() < 1e-6 {
+        (0.0, e)
+    } else {
+        ((b * e - dd) / denom, (e - b * dd) / denom)
+    };
+    // The projectile has to cross the ray in front of the player
+    if s < 0.0 {
+        s = 0.0;
+        t = e;
+    }
+    length((eye.position + d * s) - (origin + u * t))
+}
+
+// Angle between the grenade's launch velocity and what the eye angles would have launched
+fn grenade_deviation(eye: &Eye, velocity: Vector) -> f32 {
+    let (forward, _, up) = angle_vectors(eye.yaw, eye.pitch);
+    let speed = (length(velocity).powi(2) - GRENADE_UP_SPEED.powi(2)).max(0.0).sqrt();
+    vector_angle(velocity, forward * speed + up * GRENADE_UP_SPEED)
+}
+
+#[derive(Default)]
+struct ShotStats {
+    shots: u32,
+    mismatches: Vec<Value>,
+}
+
+#[derive(Default)]
+pub struct ProjectileAim {
+    params: Parameters,
+
+    eyes: HashMap<EntityId, Eye>,
+    seen_projectiles: HashMap<EntityId, DemoTick>,
+    stats: HashMap<u64, ShotStats>,
+}
+
+impl ProjectileAim {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                // Player movement between the shot and the snapshot shifts the eye by a few units
+                ("max_miss_distance".to_string(), Parameter::Float(24.0)),
+                // Grenade launchers add up to 10 u/s of random spread
+                ("max_grenade_deviation".to_string(), Parameter::Float(3.0)),
+                ("min_mismatches".to_string(), Parameter::Int(3)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    // How far off the projectile is from the eye, and whether that's a mismatch
+    fn check(
+        &self,
+        ty: &ProjectileType,
+        eye: &Eye,
+        origin: Vector,
+        velocity: Vector,
+    ) -> Option<(f32, bool)> {
+        let max_miss_distance: f32 = get_parameter_value(&self.params, "max_miss_distance");
+        let max_grenade_deviation: f32 = get_parameter_value(&self.params, "max_grenade_deviation");
+        match ty {
+            ProjectileType::Rocket
+            | ProjectileType::Flare
+            | ProjectileType::Arrow
+            | ProjectileType::HealingBolt => {
+                let miss = miss_distance(eye, origin, velocity);
+                Some((miss, miss > max_miss_distance))
+            }
+            ProjectileType::Pipe | ProjectileType::Sticky | ProjectileType::LooseCannon => {
+                let deviation = grenade_deviation(eye, velocity);
+                Some((deviation, deviation > max_grenade_deviation))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for ProjectileAim {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "projectile_aim"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let eyes: HashMap<EntityId, Eye> = state
+            .players
+            .iter()
+            .filter(|p| p.in_pvs && p.state == PlayerState::Alive)
+            .map(|p| {
+                (
+                    p.entity_id(),
+                    Eye {
+                        position: p.eye_position(),
+                        yaw: p.view_angle,
+                        pitch: p.pitch_angle,
+                    },
+                )
+            })
+            .collect();
+
+        self.seen_projectiles
+            .retain(|id, _| state.projectiles.contains_key(id));
+
+        for (id, projectile) in &state.projectiles {
+            if self.seen_projectiles.insert(*id, projectile.spawn_tick)
+                == Some(projectile.spawn_tick)
+            {
+                continue;
+            }
+            if projectile.deflected > 0 || length(projectile.initial_velocity) <= 0.0 {
+                continue;
+            }
+            let (Some(after), Some(before)) =
+                (eyes.get(&projectile.owner), self.eyes.get(&projectile.owner))
+            else {
+                continue;
+            };
+            let Some(player) = state.get_player_by_entid(projectile.owner) else {
+                continue;
+            };
+            let Some(info) = &player.info else {
+                continue;
+            };
+            if info.steam_id == "BOT" {
+                continue;
+            }
+            let origin = projectile.position;
+            let velocity = projectile.initial_velocity;
+            let (Some((off_before, mismatch_before)), Some((off_after, mismatch_after))) = (
+                self.check(&projectile.ty, before, origin, velocity),
+                self.check(&projectile.ty, after, origin, velocity),
+            ) else {
+                continue;
+            };
+
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            let stats = self.stats.entry(steam_id).or_default();
+            stats.shots += 1;
+            if mismatch_before && mismatch_after {
+                let (forward, _, _) = angle_vectors(after.yaw, after.pitch);
+                stats.mismatches.push(json!({
+                    "tick": u32::from(projectile.spawn_tick),
+                    "projectile": format!("{:?}", projectile.ty),
+                    "weapon": state
+                        .weapons
+                        .get(&projectile.launcher)
+                        .map_or(Value::Null, |w| item_json(w.item_definition_index)),
+                    "offset_before": off_before,
+                    "offset_after": off_after,
+                    "eye_angles": [after.pitch, after.yaw],
+                    "angle_to_eye": vector_angle(velocity, forward),
+                }));
+            }
+        }
+
+        self.eyes = eyes;
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_mismatches: i32 = get_parameter_value(&self.params, "min_mismatches");
+
+        let mut detections = Vec::new();
+        for (steam_id, stats) in &self.stats {
+            if (stats.mismatches.len() as i32) < min_mismatches {
+                continue;
+            }
+            detections.push(Detection {
+                tick: stats.mismatches[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "shots": stats.shots,
+                    "mismatches": stats.mismatches.len(),
+                    "mismatch_ratio": stats.mismatches.len() as f32 / stats.shots.max(1) as f32,
+                    "shots_mismatched": stats.mismatches,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::angle_delta;
+use crate::util::items_game::item_json;
+use crate::util::shots::{shots_fired, ShotSource};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::ParserState;
+
+// Compares the angles hitscan shots were fired at (CTEFireBullets) with the player's eye angles.
+// Silent aim sends the aimbot's angles only in the usercmd that shoots, so the snapshots right before
+// and after the shot both show the player looking somewhere else. A shot is only flagged when it isn't
+// on the way between those two view directions, so fast legit flicks don't count.
+
+struct PendingShot {
+    steam_id: u64,
+    entity: EntityId,
+    tick: u32,
+    // (yaw, pitch)
+    shot: (f32, f32),
+    before: (f32, f32),
+    weapon: Value,
+}
+
+#[derive(Default)]
+pub struct SilentAim {
+    params: Parameters,
+
+    // Eye angles of every player as of the previous snapshot
+    angles: HashMap<EntityId, (f32, f32)>,
+    pending: Vec<PendingShot>,
+}
+
+impl SilentAim {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([("max_deviation".to_string(), Parameter::Float(5.0))]),
+            ..Default::default()
+        }
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for SilentAim {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "silent_aim"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let max_deviation: f32 = get_parameter_value(&self.params, "max_deviation");
+
+        let angles: HashMap<EntityId, (f32, f32)> = state
+            .players
+            .iter()
+            .filter(|p| p.in_pvs && p.state == PlayerState::Alive)
+            .map(|p| (p.entity_id(), (p.view_angle, p.pitch_angle)))
+            .collect();
+
+        let mut detections = Vec::new();
+        for shot in std::mem::take(&mut self.pending) {
+            let Some(after) = angles.get(&shot.entity).copied() else {
+                continue;
+            };
+            let deviation_before = angle_delta(shot.shot, shot.before);
+            let deviation_after = angle_delta(shot.shot, after);
+            let spread = angle_delta(shot.before, after);
+            if deviation_before <= max_deviation
+                || deviation_after <= max_deviation
+                || deviation_before + deviation_after <= spread + max_deviation
+            {
+                continue;
+            }
+            detections.push(Detection {
+                tick: shot.tick,
+                algorithm: self.algorithm_name().to_string(),
+                player: shot.steam_id,
+                data: json!({
+                    "shot_angles": [shot.shot.1, shot.shot.0],
+                    "eye_angles_before": [shot.before.1, shot.before.0],
+                    "eye_angles_after": [after.1, after.0],
+                    "deviation_before": deviation_before,
+                    "deviation_after": deviation_after,
+                    "weapon": shot.weapon,
+                }),
+            });
+        }
+
+        self.angles = angles;
+        Ok(detections)
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::TempEntities])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        parser_state: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let Message::TempEntities(msg) = message else {
+            return Ok(vec![]);
+        };
+        for shot in shots_fired(msg, parser_state) {
+            let ShotSource::Bullets { yaw, pitch } = shot.source else {
+                continue;
+            };
+            let entity = shot.entity;
+            let Some(before) = self.angles.get(&entity).copied() else {
+                continue;
+            };
+            let Some(player) = state.get_player_by_entid(entity) else {
+                continue;
+            };
+            let Some(info) = &player.info else {
+                continue;
+            };
+            if info.steam_id == "BOT" {
+                continue;
+            }
+            self.pending.push(PendingShot {
+                steam_id: u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default()),
+                entity,
+                tick: u32::from(tick),
+                shot: (yaw, pitch),
+                before,
+                weapon: state
+                    .get_active_weapon(player)
+                    .map_or(Value::Null, |w| item_json(w.item_definition_index)),
+            });
+        }
+        Ok(vec![])
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
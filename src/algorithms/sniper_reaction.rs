@@ -0,0 +1,275 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, Class, Player, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{angle_delta, angles_to, length};
+use crate::util::items_game::item_json;
+use crate::util::visibility::line_of_sight;
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::gameevent_gen::GameEvent;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::ParserState;
+
+// Measures, for every Sniper headshot, how long the victim had been visible to the Sniper and the flick
+// that lined up the shot. A visible victim is one that is alive, in PVS, inside the Sniper's field of
+// view and not behind a wall (if the map was loaded). Humans need a couple hundred milliseconds to
+// notice a target, and their fast flicks overshoot or stop short of the head. Aimbots shoot almost as
+// soon as a head shows up, after a flick that ends right on it. The distributions are reported per
+// Sniper, who is flagged when both medians beat what a human can do. Distributions of unflagged
+// Snipers are only reported with report_all.
+
+const TICK_INTERVAL: f32 = 0.015;
+// TF_DMG_CUSTOM_HEADSHOT and TF_DMG_CUSTOM_HEADSHOT_DECAPITATION
+const DMG_CUSTOM_HEADSHOT: u16 = 1;
+const DMG_CUSTOM_HEADSHOT_DECAPITATION: u16 = 51;
+// Sniper views kept per tick
+const HISTORY_TICKS: usize = 66;
+
+fn median(values: &mut [f32]) -> Option<f32> {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values.get(values.len() / 2).copied()
+}
+
+#[derive(Default)]
+struct SniperTrack {
+    // (tick, yaw, pitch)
+    views: VecDeque<(u32, f32, f32)>,
+    // Tick every enemy became visible, by enemy entity
+    visible_since: HashMap<EntityId, u32>,
+}
+
+#[derive(Default)]
+pub struct SniperReaction {
+    params: Parameters,
+
+    snipers: HashMap<EntityId, SniperTrack>,
+    headshots: HashMap<u64, Vec<Value>>,
+}
+
+impl SniperReaction {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                // Half of the horizontal field of view at the default fov of 90 on a 16:9 screen
+                ("view_fov".to_string(), Parameter::Float(53.0)),
+                // Largest view change over this many ticks before the shot counts as its flick
+                ("flick_ticks".to_string(), Parameter::Int(20)),
+                ("min_flick_angle".to_string(), Parameter::Float(10.0)),
+                ("min_headshots".to_string(), Parameter::Int(5)),
+                ("max_median_reaction_ms".to_string(), Parameter::Float(200.0)),
+                // Degrees between the crosshair at the shot and the victim's head, over flicks only
+                ("max_median_flick_error".to_string(), Parameter::Float(0.5)),
+                ("report_all".to_string(), Parameter::Bool(false)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn is_sniper(player: &Player) -> bool {
+        player.in_pvs && player.state == PlayerState::Alive && player.class == Class::Sniper
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for SniperReaction {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "sniper_reaction"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let view_fov: f32 = get_parameter_value(&self.params, "view_fov");
+        let ticknum = u32::from(state.tick);
+
+        let snipers: Vec<&Player> = state.players.iter().filter(|p| Self::is_sniper(p)).collect();
+        self.snipers
+            .retain(|sniper, _| snipers.iter().any(|p| p.entity_id() == *sniper));
+
+        for sniper in snipers {
+            let track = self.snipers.entry(sniper.entity_id()).or_default();
+            track.views.push_back((ticknum, sniper.view_angle, sniper.pitch_angle));
+            if track.views.len() > HISTORY_TICKS {
+                track.views.pop_front();
+            }
+
+            let visible: Vec<EntityId> = state
+                .get_aim(sniper)
+                .into_iter()
+                .flat_map(|aim| &aim.targets)
+                .filter(|t| t.angle() <= view_fov)
+                .filter(|t| {
+                    state
+                        .get_player_by_entid(t.entity)
+                        .is_some_and(|other| line_of_sight(state, sniper, other))
+                })
+                .map(|t| t.entity)
+                .collect();
+            track.visible_since.retain(|enemy, _| visible.contains(enemy));
+            for enemy in visible {
+                track.visible_since.entry(enemy).or_insert(ticknum);
+            }
+        }
+
+        Ok(vec![])
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::GameEvent])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        _: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let flick_ticks: i32 = get_parameter_value(&self.params, "flick_ticks");
+
+        let Message::GameEvent(event_msg) = message else {
+            return Ok(vec![]);
+        };
+        let GameEvent::PlayerHurt(hurt) = &event_msg.event else {
+            return Ok(vec![]);
+        };
+        if hurt.custom != DMG_CUSTOM_HEADSHOT && hurt.custom != DMG_CUSTOM_HEADSHOT_DECAPITATION {
+            return Ok(vec![]);
+        }
+        let find_player = |user_id: u16| {
+            state.players.iter().find(|p| {
+                p.info
+                    .as_ref()
+                    .is_some_and(|info| u16::from(info.user_id) == user_id)
+            })
+        };
+        let (Some(sniper), Some(victim)) = (find_player(hurt.attacker), find_player(hurt.user_id))
+        else {
+            return Ok(vec![]);
+        };
+        if !Self::is_sniper(sniper) {
+            return Ok(vec![]);
+        }
+        let Some(info) = &sniper.info else {
+            return Ok(vec![]);
+        };
+        if info.steam_id == "BOT" {
+            return Ok(vec![]);
+        }
+        let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+        let Some(track) = self.snipers.get(&sniper.entity_id()) else {
+            return Ok(vec![]);
+        };
+        let Some(&(_, yaw, pitch)) = track.views.back() else {
+            return Ok(vec![]);
+        };
+        let tick = u32::from(tick);
+
+        // The flick starts at the view furthest from the one the shot was fired with
+        let start = tick.saturating_sub(flick_ticks.max(0) as u32);
+        let flick = track
+            .views
+            .iter()
+            .filter(|(t, _, _)| *t >= start)
+            .map(|(t, y, p)| (*t, angle_delta((*y, *p), (yaw, pitch))))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let head_error = angle_delta(
+            (yaw, pitch),
+            angles_to(sniper.eye_position(), victim.eye_position()),
+        );
+        let reaction_ticks = track
+            .visible_since
+            .get(&victim.entity_id())
+            .map(|since| tick.saturating_sub(*since));
+
+        self.headshots.entry(steam_id).or_default().push(json!({
+            "tick": tick,
+            "victim": state.get_id64_from_entid(victim.entity_id()),
+            "killed": hurt.health == 0,
+            "weapon": state
+                .get_active_weapon(sniper)
+                .map_or(Value::Null, |w| item_json(w.item_definition_index)),
+            "distance": length(victim.eye_position() - sniper.eye_position()),
+            // Null if the victim was never seen inside the Sniper's view
+            "reaction_ticks": reaction_ticks,
+            "reaction_ms": reaction_ticks.map(|t| t as f32 * TICK_INTERVAL * 1000.0),
+            "flick_angle": flick.map_or(0.0, |f| f.1),
+            "flick_ticks": flick.map_or(0, |f| tick.saturating_sub(f.0)),
+            "head_error": head_error,
+        }));
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_flick_angle: f32 = get_parameter_value(&self.params, "min_flick_angle");
+        let min_headshots: i32 = get_parameter_value(&self.params, "min_headshots");
+        let max_median_reaction_ms: f32 = get_parameter_value(&self.params, "max_median_reaction_ms");
+        let max_median_flick_error: f32 = get_parameter_value(&self.params, "max_median_flick_error");
+        let report_all: bool = get_parameter_value(&self.params, "report_all");
+
+        let mut detections = Vec::new();
+        for (steam_id, headshots) in &self.headshots {
+            let mut reactions: Vec<f32> = headshots
+                .iter()
+                .filter_map(|h| h["reaction_ms"].as_f64())
+                .map(|r| r as f32)
+                .collect();
+            let mut flick_errors: Vec<f32> = headshots
+                .iter()
+                .filter(|h| h["flick_angle"].as_f64().unwrap_or_default() as f32 >= min_flick_angle)
+                .filter_map(|h| h["head_error"].as_f64())
+                .map(|e| e as f32)
+                .collect();
+            let (flicks, reacted) = (flick_errors.len(), reactions.len());
+            let median_reaction_ms = median(&mut reactions);
+            let median_flick_error = median(&mut flick_errors);
+
+            let flagged = headshots.len() as i32 >= min_headshots
+                && median_reaction_ms.is_some_and(|r| r <= max_median_reaction_ms)
+                && median_flick_error.is_some_and(|e| e <= max_median_flick_error);
+            if !flagged && !report_all {
+                continue;
+            }
+            let percentile = |values: &[f32], p: usize| values.get(values.len() * p / 100).copied();
+            detections.push(Detection {
+                tick: headshots[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "flagged": flagged,
+                    "headshots": headshots.len(),
+                    "timed_headshots": reacted,
+                    "flicks": flicks,
+                    "median_reaction_ms": median_reaction_ms,
+                    "reaction_ms_percentiles": {
+                        "10": percentile(&reactions, 10),
+                        "25": percentile(&reactions, 25),
+                        "75": percentile(&reactions, 75),
+                        "90": percentile(&reactions, 90),
+                    },
+                    "median_flick_error": median_flick_error,
+                    "flick_error_percentiles": {
+                        "10": percentile(&flick_errors, 10),
+                        "25": percentile(&flick_errors, 25),
+                        "75": percentile(&flick_errors, 75),
+                        "90": percentile(&flick_errors, 90),
+                    },
+                    "shots": headshots,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::base::cheat_analyser_base::{
+    CheatAnalyserState, Class, Player, PlayerCondition, PlayerState,
+};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::items_game::{get_item, items_game_loaded};
+
+use anyhow::Error;
+use serde_json::json;
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::gameevent_gen::GameEvent;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::demo::vector::Vector;
+use tf_demo_parser::ParserState;
+
+// Flags players moving along the ground faster than their class, items and conditions allow.
+// Speed is measured over server ticks rather than simulation ticks: a speedhack runs extra usercmds,
+// so every simulated tick looks normal while the player covers more ground per server tick.
+// Samples in the air, while charging/grappling/etc. or shortly after a teleporter or spawn are skipped,
+// and large jumps are counted as movement instead of being written off as teleports.
+// Item speed bonuses and penalties come from items_game.txt, so nothing is checked without it. Only
+// weapons are networked per player, so a Demoman without a primary weapon is assumed to be wearing
+// speed boosting boots.
+
+const TICK_INTERVAL: f32 = 0.015;
+// tf_maxspeed, nothing but charging and karts goes faster
+const TF_MAX_SPEED: f32 = 520.0;
+const CHARGE_SPEED: f32 = 750.0;
+// Decapitating swords add 8% speed per head, up to 4 heads
+const HEAD_SPEED_BONUS: f32 = 0.08;
+const MAX_SPEED_HEADS: u8 = 4;
+// Ali Baba's Wee Booties and the Bootlegger
+const BOOTS_SPEED_MULT: f32 = 1.1;
+
+fn class_max_speed(class: Class) -> f32 {
+    match class {
+        Class::Scout => 400.0,
+        Class::Soldier => 240.0,
+        Class::Pyro => 300.0,
+        Class::Demoman => 280.0,
+        Class::Heavy => 230.0,
+        Class::Engineer => 300.0,
+        Class::Medic => 320.0,
+        Class::Sniper => 300.0,
+        Class::Spy => 320.0,
+        Class::Other => TF_MAX_SPEED,
+    }
+}
+
+// Movement that isn't limited by the player's max speed
+fn is_exempt(player: &Player) -> bool {
+    use PlayerCondition::*;
+    !player.on_ground()
+        || [
+            BlastJumping,
+            KnockedIntoAir,
+            GrapplingHook,
+            GrapplingHookLatched,
+            GrappledToPlayer,
+            GrappledByPlayer,
+            HalloweenKart,
+            HalloweenKartDash,
+            ParachuteActive,
+            AirCurrent,
+        ]
+        .into_iter()
+        .any(|cond| player.has_condition(cond))
+}
+
+struct Sample {
+    server_tick: u32,
+    position: Vector,
+}
+
+struct OverSpeed {
+    start_tick: u32,
+    end_tick: u32,
+    peak_speed: f32,
+    speed_sum: f32,
+    samples: u32,
+    max_speed: f32,
+}
+
+#[derive(Default)]
+struct MovementTracker {
+    samples: VecDeque<Sample>,
+    // Tick until which movement is ignored (spawn, teleporter, landing)
+    grace_until: u32,
+    over_speed: Option<OverSpeed>,
+}
+
+#[derive(Default)]
+pub struct Speedhack {
+    params: Parameters,
+
+    trackers: HashMap<u64, MovementTracker>,
+    // Server tick of the entity data currently in the state (see Tickbase)
+    data_server_tick: u32,
+}
+
+impl Speedhack {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("window_ticks".to_string(), Parameter::Int(33)),
+                ("min_duration_ticks".to_string(), Parameter::Int(132)),
+                ("tolerance".to_string(), Parameter::Float(0.1)),
+                ("grace_ticks".to_string(), Parameter::Int(33)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    // Fastest the player could legitimately move along the ground right now
+    fn max_speed(state: &CheatAnalyserState, player: &Player) -> f32 {
+        if player.has_condition(PlayerCondition::ShieldCharge) {
+            return CHARGE_SPEED;
+        }
+        let mut speed = Self::own_max_speed(state, player);
+        // A Medic keeps up with whoever they are healing
+        if player.class == Class::Medic {
+            let patient = state
+                .weapons
+                .values()
+                .filter(|w| w.owner == player.entity_id())
+                .find_map(|w| w.medigun.filter(|medigun| medigun.healing))
+                .and_then(|medigun| state.get_player_by_entid(medigun.healing_target));
+            if let Some(patient) = patient {
+                speed = speed.max(Self::own_max_speed(state, patient));
+            }
+        }
+        speed.min(TF_MAX_SPEED)
+    }
+
+    // Max speed from the player's own class, items and conditions
+    fn own_max_speed(state: &CheatAnalyserState, player: &Player) -> f32 {
+        let mut speed = class_max_speed(player.class);
+        let mut has_primary = false;
+        let mut has_sword = false;
+        for weapon in state
+            .weapons
+            .values()
+            .filter(|w| w.owner == player.entity_id())
+        {
+            has_primary |= matches!(
+                weapon.class_name.as_str(),
+                "CTFGrenadeLauncher" | "CTFCannon" | "CTFParachute_Primary"
+            );
+            has_sword |= weapon.class_name == "CTFSword";
+            match get_item(weapon.item_definition_index).map(|item| item.move_speed_multiplier()) {
+                Some(Some(multiplier)) => speed *= multiplier,
+                Some(None) => speed = TF_MAX_SPEED,
+                None => {}
+            }
+        }
+        if player.class == Class::Demoman {
+            if has_sword {
+                let heads = player.decapitations.min(MAX_SPEED_HEADS);
+                speed *= 1.0 + HEAD_SPEED_BONUS * heads as f32;
+            }
+            if !has_primary {
+                speed *= BOOTS_SPEED_MULT;
+            }
+        }
+        if player.has_condition(PlayerCondition::SpeedBoost) {
+            speed += (speed * 0.4).min(105.0);
+        }
+        if player.has_condition(PlayerCondition::HalloweenSpeedBoost)
+            || player.has_condition(PlayerCondition::RuneHaste)
+        {
+            speed = TF_MAX_SPEED;
+        }
+        speed.min(TF_MAX_SPEED)
+    }
+
+    fn end_over_speed(&self, steam_id: u64, over_speed: OverSpeed) -> Option<Detection> {
+        let min_duration_ticks: i32 = get_parameter_value(&self.params, "min_duration_ticks");
+        let duration = over_speed.end_tick - over_speed.start_tick;
+        if (duration as i32) < min_duration_ticks {
+            return None;
+        }
+        Some(Detection {
+            tick: over_speed.start_tick,
+            algorithm: self.algorithm_name().to_string(),
+            player: steam_id,
+            data: json!({
+                "start_tick": over_speed.start_tick,
+                "end_tick": over_speed.end_tick,
+                "duration_ticks": duration,
+                "max_speed": over_speed.max_speed,
+                "peak_speed": over_speed.peak_speed,
+                "mean_speed": over_speed.speed_sum / over_speed.samples.max(1) as f32,
+            }),
+        })
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for Speedhack {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "speedhack"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let server_tick = self.data_server_tick;
+        self.data_server_tick = u32::from(state.server_tick);
+        if server_tick == 0 || !items_game_loaded() {
+            return Ok(vec![]);
+        }
+        let ticknum = u32::from(state.tick);
+
+        let window_ticks: i32 = get_parameter_value(&self.params, "window_ticks");
+        let tolerance: f32 = get_parameter_value(&self.params, "tolerance");
+        let grace_ticks: i32 = get_parameter_value(&self.params, "grace_ticks");
+
+        let mut detections = Vec::new();
+        let mut trackers = std::mem::take(&mut self.trackers);
+
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            let tracker = trackers.entry(steam_id).or_default();
+
+            let tracked =
+                player.in_pvs && player.state == PlayerState::Alive && info.steam_id != "BOT";
+            if !tracked || is_exempt(player) {
+                // Landing from a jump or knockback keeps some of the air speed for a moment
+                if tracked {
+                    tracker.grace_until = ticknum + grace_ticks.max(0) as u32;
+                }
+                tracker.samples.clear();
+                if let Some(over_speed) = tracker.over_speed.take() {
+                    detections.extend(self.end_over_speed(steam_id, over_speed));
+                }
+                continue;
+            }
+            if ticknum < tracker.grace_until {
+                tracker.samples.clear();
+                continue;
+            }
+
+            if tracker.samples.back().is_some_and(|s| s.server_tick >= server_tick) {
+                continue;
+            }
+            tracker.samples.push_back(Sample {
+                server_tick,
+                position: player.position,
+            });
+            while tracker
+                .samples
+                .get(1)
+                .is_some_and(|s| (server_tick - s.server_tick) as i32 >= window_ticks)
+            {
+                tracker.samples.pop_front();
+            }
+            let Some(first) = tracker.samples.front() else {
+                continue;
+            };
+            let elapsed = server_tick - first.server_tick;
+            if (elapsed as i32) < window_ticks {
+                continue;
+            }
+
+            let distance: f32 = tracker
+                .samples
+                .iter()
+                .zip(tracker.samples.iter().skip(1))
+                .map(|(a, b)| (b.position.x - a.position.x).hypot(b.position.y - a.position.y))
+                .sum();
+            let speed = distance / (elapsed as f32 * TICK_INTERVAL);
+            let max_speed = Self::max_speed(state, player);
+
+            if speed > max_speed * (1.0 + tolerance) {
+                let over_speed = tracker.over_speed.get_or_insert(OverSpeed {
+                    start_tick: ticknum,
+                    end_tick: ticknum,
+                    peak_speed: 0.0,
+                    speed_sum: 0.0,
+                    samples: 0,
+                    max_speed,
+                });
+                over_speed.end_tick = ticknum;
+                over_speed.peak_speed = over_speed.peak_speed.max(speed);
+                over_speed.speed_sum += speed;
+                over_speed.samples += 1;
+                over_speed.max_speed = over_speed.max_speed.max(max_speed);
+            } else if let Some(over_speed) = tracker.over_speed.take() {
+                detections.extend(self.end_over_speed(steam_id, over_speed));
+            }
+        }
+
+        self.trackers = trackers;
+        Ok(detections)
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::GameEvent])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        _: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let Message::GameEvent(event_msg) = message else {
+            return Ok(vec![]);
+        };
+        let user_id = match &event_msg.event {
+            GameEvent::PlayerSpawn(spawn) => spawn.user_id,
+            GameEvent::PlayerTeleported(tele) => tele.user_id,
+            _ => return Ok(vec![]),
+        };
+        let Some(steam_id) = state.get_id64_from_userid(user_id.into()) else {
+            return Ok(vec![]);
+        };
+
+        let grace_ticks: i32 = get_parameter_value(&self.params, "grace_ticks");
+        let tracker = self.trackers.entry(steam_id).or_default();
+        tracker.grace_until = u32::from(tick) + grace_ticks.max(0) as u32;
+        tracker.samples.clear();
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let mut detections = Vec::new();
+        for (steam_id, tracker) in std::mem::take(&mut self.trackers) {
+            if let Some(over_speed) = tracker.over_speed {
+                detections.extend(self.end_over_speed(steam_id, over_speed));
+            }
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
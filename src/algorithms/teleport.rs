@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{
+    Building, CheatAnalyserState, Player, PlayerCondition, PlayerState,
+};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::nocrex::jankguard::{TELEPORT_DIST, TELEPORT_STEP_RATIO};
+
+use anyhow::Error;
+use serde_json::json;
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::gameevent_gen::GameEvent;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::demo::vector::Vector;
+use tf_demo_parser::ParserState;
+
+// Checks every position jump that JankGuard would call a teleport against the things that can
+// legitimately move a player that far: teleporter events, spawns, standing on a teleporter entrance,
+// the Eureka Effect and Halloween teleports. Teleport events and spawns can arrive a few ticks away
+// from the jump, so those are checked at the end, together with map teleports (trigger_teleport),
+// which are recognised by another player arriving at the same spot within a couple of seconds.
+
+fn distance(a: Vector, b: Vector) -> f32 {
+    let diff = a - b;
+    (diff.x.powi(2) + diff.y.powi(2) + diff.z.powi(2)).sqrt()
+}
+
+// Conditions that come with a scripted teleport
+fn in_teleporting_condition(player: &Player) -> bool {
+    use PlayerCondition::*;
+    [
+        SelectedToTeleport, // Eureka Effect
+        HalloweenInHell,
+        HalloweenGhostMode,
+        HalloweenKart,
+        PURGATORY,
+    ]
+    .into_iter()
+    .any(|cond| player.has_condition(cond))
+}
+
+struct Jump {
+    steam_id: u64,
+    tick: u32,
+    from: Vector,
+    to: Vector,
+    server_ticks: u32,
+}
+
+struct LastPosition {
+    server_tick: u32,
+    position: Vector,
+    step: f32,
+    teleporting: bool,
+}
+
+#[derive(Default)]
+pub struct UnexplainedTeleport {
+    params: Parameters,
+
+    last_positions: HashMap<u64, LastPosition>,
+    // Ticks of spawn and teleporter events per player
+    events: HashMap<u64, Vec<u32>>,
+    jumps: Vec<Jump>,
+    // Server tick of the entity data currently in the state (see Tickbase)
+    data_server_tick: u32,
+}
+
+impl UnexplainedTeleport {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("event_window_ticks".to_string(), Parameter::Int(66)),
+                ("teleporter_radius".to_string(), Parameter::Float(96.0)),
+                ("map_teleport_radius".to_string(), Parameter::Float(128.0)),
+                ("map_teleport_window_ticks".to_string(), Parameter::Int(132)),
+                // Longer gaps between updates aren't a single jump
+                ("max_gap_ticks".to_string(), Parameter::Int(8)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    // Player standing on a built entrance and arriving at a built exit
+    fn used_teleporter(&self, state: &CheatAnalyserState, from: Vector, to: Vector) -> bool {
+        let radius: f32 = get_parameter_value(&self.params, "teleporter_radius");
+        state.buildings.values().any(|building| match building {
+            Building::Teleporter(entrance) if entrance.is_entrance => {
+                distance(entrance.position, from) <= radius
+                    && state.buildings.values().any(|exit| match exit {
+                        Building::Teleporter(exit) if !exit.is_entrance => {
+                            distance(exit.position, to) <= radius
+                        }
+                        _ => false,
+                    })
+            }
+            _ => false,
+        })
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for UnexplainedTeleport {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "unexplained_teleport"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let server_tick = self.data_server_tick;
+        self.data_server_tick = u32::from(state.server_tick);
+        if server_tick == 0 {
+            return Ok(vec![]);
+        }
+        let max_gap_ticks: i32 = get_parameter_value(&self.params, "max_gap_ticks");
+
+        let mut positions = HashMap::new();
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            if !player.in_pvs || player.state != PlayerState::Alive || info.steam_id == "BOT" {
+                continue;
+            }
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            let teleporting = in_teleporting_condition(player);
+
+            let mut step = 0.0;
+            if let Some(last) = self.last_positions.get(&steam_id) {
+                let elapsed = server_tick.saturating_sub(last.server_tick);
+                step = distance(last.position, player.position);
+                if elapsed > 0
+                    && elapsed as i32 <= max_gap_ticks
+                    && step > TELEPORT_DIST
+                    && step > last.step * TELEPORT_STEP_RATIO
+                    && !teleporting
+                    && !last.teleporting
+                    && !self.used_teleporter(state, last.position, player.position)
+                {
+                    self.jumps.push(Jump {
+                        steam_id,
+                        tick: u32::from(state.tick),
+                        from: last.position,
+                        to: player.position,
+                        server_ticks: elapsed,
+                    });
+                }
+            }
+            positions.insert(
+                steam_id,
+                LastPosition {
+                    server_tick,
+                    position: player.position,
+                    step,
+                    teleporting,
+                },
+            );
+        }
+        // Players that died or left PVS start over
+        self.last_positions = positions;
+
+        Ok(vec![])
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::GameEvent])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        _: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let Message::GameEvent(event_msg) = message else {
+            return Ok(vec![]);
+        };
+        let user_id = match &event_msg.event {
+            GameEvent::PlayerSpawn(spawn) => spawn.user_id,
+            GameEvent::PostInventoryApplication(app) => app.user_id,
+            GameEvent::PlayerTeleported(tele) => tele.user_id,
+            _ => return Ok(vec![]),
+        };
+        if let Some(steam_id) = state.get_id64_from_userid(user_id.into()) {
+            self.events.entry(steam_id).or_default().push(u32::from(tick));
+        }
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let event_window_ticks: i32 = get_parameter_value(&self.params, "event_window_ticks");
+        let map_teleport_radius: f32 = get_parameter_value(&self.params, "map_teleport_radius");
+        let map_teleport_window_ticks: i32 =
+            get_parameter_value(&self.params, "map_teleport_window_ticks");
+
+        let mut detections = Vec::new();
+        for jump in &self.jumps {
+            let explained_by_event = self.events.get(&jump.steam_id).is_some_and(|ticks| {
+                ticks
+                    .iter()
+                    .any(|t| (*t as i64 - jump.tick as i64).abs() <= event_window_ticks as i64)
+            });
+            if explained_by_event {
+                continue;
+            }
+            // A trigger_teleport sends everyone to the same destination
+            let map_teleport = self.jumps.iter().any(|other| {
+                other.steam_id != jump.steam_id
+                    && (other.tick as i64 - jump.tick as i64).abs()
+                        <= map_teleport_window_ticks as i64
+                    && distance(other.to, jump.to) <= map_teleport_radius
+            });
+            if map_teleport {
+                continue;
+            }
+
+            detections.push(Detection {
+                tick: jump.tick,
+                algorithm: self.algorithm_name().to_string(),
+                player: jump.steam_id,
+                data: json!({
+                    "from": [jump.from.x, jump.from.y, jump.from.z],
+                    "to": [jump.to.x, jump.to.y, jump.to.z],
+                    "distance": distance(jump.from, jump.to),
+                    "server_ticks": jump.server_ticks,
+                }),
+            });
+        }
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
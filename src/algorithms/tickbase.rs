@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::ParserState;
+
+// Tracks each player's simulation tick (decoded from m_flSimulationTime) against the server tick.
+// A player is only simulated when the server receives their usercmds, so:
+// - choking packets shows up as the simulation tick stalling, then catching up in one burst
+// - warping/doubletap shows up as a burst that simulates more ticks than actually passed
+// - a simulation tick moving backwards means the tickbase was shifted
+
+// Simulation tick of a player as of the last snapshot
+struct SimtimeTracker {
+    sim_tick: u32,
+    // Server tick at which the simulation tick last advanced
+    last_advance: u32,
+}
+
+#[derive(Default)]
+pub struct Tickbase {
+    params: Parameters,
+
+    trackers: HashMap<u64, SimtimeTracker>,
+    chokes: HashMap<u64, Vec<Value>>,
+    // Server tick of the entity data currently in the state. The NetTick message that triggers
+    // on_tick arrives before the entities of its own packet, so this lags one packet behind.
+    data_server_tick: u32,
+    last_tick: u32,
+}
+
+impl Tickbase {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("min_choke_ticks".to_string(), Parameter::Int(12)),
+                ("min_choke_events".to_string(), Parameter::Int(20)),
+                // Clock correction can legitimately move a player's tickbase forward by a few ticks
+                ("warp_tolerance_ticks".to_string(), Parameter::Int(5)),
+                ("max_lead_ticks".to_string(), Parameter::Int(8)),
+                ("backwards_tolerance_ticks".to_string(), Parameter::Int(4)),
+            ]),
+            ..Default::default()
+        }
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for Tickbase {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "tickbase"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let server_tick = self.data_server_tick;
+        self.data_server_tick = u32::from(state.server_tick);
+        self.last_tick = u32::from(state.tick);
+        if server_tick == 0 {
+            return Ok(vec![]);
+        }
+
+        let min_choke_ticks: i32 = get_parameter_value(&self.params, "min_choke_ticks");
+        let warp_tolerance_ticks: i32 = get_parameter_value(&self.params, "warp_tolerance_ticks");
+        let max_lead_ticks: i32 = get_parameter_value(&self.params, "max_lead_ticks");
+        let backwards_tolerance_ticks: i32 =
+            get_parameter_value(&self.params, "backwards_tolerance_ticks");
+
+        let mut detections = Vec::new();
+        let mut tracked = Vec::new();
+
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            if !player.in_pvs
+                || player.state != PlayerState::Alive
+                || info.steam_id == "BOT"
+                || player.simulation_tick == 0
+            {
+                continue;
+            }
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            tracked.push(steam_id);
+
+            let sim_tick = player.simulation_tick;
+            let Some(tracker) = self.trackers.get_mut(&steam_id) else {
+                self.trackers.insert(
+                    steam_id,
+                    SimtimeTracker {
+                        sim_tick,
+                        last_advance: server_tick,
+                    },
+                );
+                continue;
+            };
+
+            // Stalling, judged once the simulation tick moves again
+            if sim_tick == tracker.sim_tick {
+                continue;
+            }
+
+            let previous_sim_tick = tracker.sim_tick;
+            let stall = server_tick.saturating_sub(tracker.last_advance);
+            tracker.sim_tick = sim_tick;
+            tracker.last_advance = server_tick;
+
+            if sim_tick < previous_sim_tick {
+                let backwards = previous_sim_tick - sim_tick;
+                if backwards as i32 > backwards_tolerance_ticks {
+                    detections.push(Detection {
+                        tick: self.last_tick,
+                        algorithm: self.algorithm_name().to_string(),
+                        player: steam_id,
+                        data: json!({
+                            "type": "backwards",
+                            "server_tick": server_tick,
+                            "sim_tick": sim_tick,
+                            "previous_sim_tick": previous_sim_tick,
+                            "backwards_ticks": backwards,
+                            "stall_ticks": stall,
+                        }),
+                    });
+                }
+                continue;
+            }
+
+            let burst = sim_tick - previous_sim_tick;
+            let lead = sim_tick as i64 - server_tick as i64;
+
+            if burst as i64 > stall as i64 + warp_tolerance_ticks as i64 || lead > max_lead_ticks as i64 {
+                detections.push(Detection {
+                    tick: self.last_tick,
+                    algorithm: self.algorithm_name().to_string(),
+                    player: steam_id,
+                    data: json!({
+                        "type": "warp",
+                        "server_tick": server_tick,
+                        "sim_tick": sim_tick,
+                        "stall_ticks": stall,
+                        "burst_ticks": burst,
+                        "excess_ticks": burst as i64 - stall as i64,
+                        "lead_ticks": lead,
+                    }),
+                });
+            } else if stall as i32 >= min_choke_ticks {
+                self.chokes.entry(steam_id).or_default().push(json!({
+                    "tick": self.last_tick,
+                    "stall_ticks": stall,
+                    "burst_ticks": burst,
+                }));
+            }
+        }
+
+        // Dead or out of PVS players have no simulation time to compare against
+        self.trackers.retain(|steam_id, _| tracked.contains(steam_id));
+
+        Ok(detections)
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_choke_events: i32 = get_parameter_value(&self.params, "min_choke_events");
+
+        let mut detections = Vec::new();
+        for (steam_id, chokes) in &self.chokes {
+            if (chokes.len() as i32) < min_choke_events {
+                continue;
+            }
+            let stalls: Vec<u64> = chokes
+                .iter()
+                .filter_map(|c| c["stall_ticks"].as_u64())
+                .collect();
+            let bursts: Vec<u64> = chokes
+                .iter()
+                .filter_map(|c| c["burst_ticks"].as_u64())
+                .collect();
+            detections.push(Detection {
+                tick: chokes[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "type": "choke",
+                    "chokes": chokes.len(),
+                    "max_stall_ticks": stalls.iter().max(),
+                    "mean_stall_ticks": stalls.iter().sum::<u64>() as f64 / stalls.len().max(1) as f64,
+                    "max_burst_ticks": bursts.iter().max(),
+                    "events": chokes,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
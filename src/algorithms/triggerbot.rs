@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::aim_targets::AimTarget;
+use crate::util::items_game::item_json;
+use crate::util::shots::shots_fired;
+use crate::util::visibility::line_of_sight;
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::Message;
+use tf_demo_parser::ParserState;
+
+// Measures how long players wait between their crosshair moving onto an enemy and firing.
+// A triggerbot fires on the first tick an enemy's hitbox crosses the crosshair, so nearly all of its
+// reaction times are 0-1 ticks, while humans need a couple hundred milliseconds and vary a lot.
+// Only the first shot after the crosshair crosses onto an enemy counts, so holding fire on a target or
+// firing at whoever happens to be in front of the crosshair doesn't.
+
+// Reaction times kept in the evidence of a detection
+const MAX_EVIDENCE_SHOTS: usize = 64;
+
+// The crosshair moved onto a target
+struct OnTarget {
+    target: EntityId,
+    target_steam_id: u64,
+    since: u32,
+    // Whether the crosshair was off every enemy right before, rather than already tracking one
+    crossed: bool,
+    fired: bool,
+}
+
+#[derive(Default)]
+struct TriggerTracker {
+    on_target: Option<OnTarget>,
+    off_target: bool,
+    reactions: Vec<Value>,
+}
+
+#[derive(Default)]
+pub struct Triggerbot {
+    params: Parameters,
+
+    trackers: HashMap<u64, TriggerTracker>,
+    // Attacks (steam id, tick, weapon) seen since the last tick, resolved once the positions of
+    // that tick are known
+    pending: Vec<(u64, u32, Value)>,
+    // Tick of the positions currently in the state. on_tick runs on the NetTick that starts a packet,
+    // before that packet's entities are read, so the positions are from the previous packet.
+    data_tick: u32,
+}
+
+impl Triggerbot {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("head_radius".to_string(), Parameter::Float(6.0)),
+                ("body_radius".to_string(), Parameter::Float(16.0)),
+                ("max_reaction_ticks".to_string(), Parameter::Int(1)),
+                ("min_shots".to_string(), Parameter::Int(20)),
+                ("min_fast_fraction".to_string(), Parameter::Float(0.8)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    // Whether the crosshair is over the target's head or body hitbox
+    fn over_hitbox(&self, target: &AimTarget) -> bool {
+        let head_radius: f32 = get_parameter_value(&self.params, "head_radius");
+        let body_radius: f32 = get_parameter_value(&self.params, "body_radius");
+        let distance = target.distance.max(1.0);
+        target.head_angle <= head_radius.atan2(distance).to_degrees()
+            || target.body_angle <= body_radius.atan2(distance).to_degrees()
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for Triggerbot {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "triggerbot"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let ticknum = std::mem::replace(&mut self.data_tick, u32::from(state.tick));
+
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            if !player.in_pvs || player.state != PlayerState::Alive || info.steam_id == "BOT" {
+                if let Some(tracker) = self.trackers.get_mut(&steam_id) {
+                    tracker.on_target = None;
+                    tracker.off_target = false;
+                }
+                continue;
+            }
+
+            let target = state
+                .get_aim(player)
+                .into_iter()
+                .flat_map(|aim| aim.targets.iter().copied())
+                .filter(|t| self.over_hitbox(t))
+                .filter(|t| {
+                    state
+                        .get_player_by_entid(t.entity)
+                        .is_some_and(|other| line_of_sight(state, player, other))
+                })
+                .min_by(|a, b| a.angle().total_cmp(&b.angle()));
+            let tracker = self.trackers.entry(steam_id).or_default();
+            match target {
+                Some(target) => {
+                    if tracker.on_target.as_ref().map(|t| t.target) != Some(target.entity) {
+                        tracker.on_target = Some(OnTarget {
+                            target: target.entity,
+                            target_steam_id: state
+                                .get_id64_from_entid(target.entity)
+                                .unwrap_or_default(),
+                            since: ticknum,
+                            crossed: tracker.off_target,
+                            fired: false,
+                        });
+                    }
+                    tracker.off_target = false;
+                }
+                None => {
+                    tracker.on_target = None;
+                    tracker.off_target = true;
+                }
+            }
+        }
+
+        for (steam_id, tick, weapon) in std::mem::take(&mut self.pending) {
+            let Some(tracker) = self.trackers.get_mut(&steam_id) else {
+                continue;
+            };
+            let Some(on_target) = tracker.on_target.as_mut() else {
+                continue;
+            };
+            if !on_target.crossed || std::mem::replace(&mut on_target.fired, true) {
+                continue;
+            }
+            tracker.reactions.push(json!({
+                "tick": tick,
+                "reaction_ticks": tick as i64 - on_target.since as i64,
+                "target": on_target.target_steam_id,
+                "weapon": weapon,
+            }));
+        }
+
+        Ok(vec![])
+    }
+
+    fn handled_messages(&self) -> Result<Vec<tf_demo_parser::MessageType>, bool> {
+        Ok(vec![tf_demo_parser::MessageType::TempEntities])
+    }
+
+    fn on_message(
+        &mut self,
+        message: &Message,
+        state: &CheatAnalyserState,
+        parser_state: &ParserState,
+        tick: DemoTick,
+    ) -> Result<Vec<Detection>, Error> {
+        let Message::TempEntities(msg) = message else {
+            return Ok(vec![]);
+        };
+        let tick = u32::from(tick);
+        for shot in shots_fired(msg, parser_state) {
+            if !shot.is_attack() {
+                continue;
+            }
+            let Some(player) = state.get_player_by_entid(shot.entity) else {
+                continue;
+            };
+            let Some(info) = &player.info else {
+                continue;
+            };
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            // Hitscan shots come with both bullets and an animation
+            if self.pending.iter().any(|(id, t, _)| *id == steam_id && *t == tick) {
+                continue;
+            }
+            let weapon = state
+                .get_active_weapon(player)
+                .map_or(Value::Null, |w| item_json(w.item_definition_index));
+            self.pending.push((steam_id, tick, weapon));
+        }
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let max_reaction_ticks: i32 = get_parameter_value(&self.params, "max_reaction_ticks");
+        let min_shots: i32 = get_parameter_value(&self.params, "min_shots");
+        let min_fast_fraction: f32 = get_parameter_value(&self.params, "min_fast_fraction");
+
+        let mut detections = Vec::new();
+        for (steam_id, tracker) in std::mem::take(&mut self.trackers) {
+            let mut shots = tracker.reactions;
+            if (shots.len() as i32) < min_shots {
+                continue;
+            }
+            shots.sort_by_key(|s| s["tick"].as_u64());
+            let mut ticks: Vec<i64> = shots
+                .iter()
+                .map(|s| s["reaction_ticks"].as_i64().unwrap_or_default())
+                .collect();
+            let fast = ticks.iter().filter(|t| **t <= max_reaction_ticks as i64).count();
+            let fast_fraction = fast as f32 / shots.len() as f32;
+            if fast_fraction < min_fast_fraction {
+                continue;
+            }
+            ticks.sort();
+            let mut histogram: HashMap<i64, u32> = HashMap::new();
+            for t in &ticks {
+                *histogram.entry((*t).min(10)).or_default() += 1;
+            }
+            let mut histogram: Vec<_> = histogram.into_iter().collect();
+            histogram.sort();
+
+            detections.push(Detection {
+                tick: shots[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: steam_id,
+                data: json!({
+                    "shots": shots.len(),
+                    "fast_shots": fast,
+                    "fast_fraction": fast_fraction,
+                    "median_reaction_ticks": ticks[ticks.len() / 2],
+                    "mean_reaction_ticks": ticks.iter().sum::<i64>() as f32 / ticks.len() as f32,
+                    // Reaction ticks (10 means 10 or more) and how many shots had them
+                    "reaction_histogram": histogram,
+                    "reactions": shots.into_iter().take(MAX_EVIDENCE_SHOTS).collect::<Vec<_>>(),
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, PlayerState};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::aim_targets::AimTarget;
+use crate::util::helpers::angles_to;
+use crate::util::visibility::{hidden_from, on_target, AimTracker, CloakTracker, Hidden, Track};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::ParserState;
+
+// Looks for players whose crosshair follows enemies they can't see, like tracking someone through a
+// wall before they come around the corner, or following a cloaked Spy. Only stretches where the
+// crosshair stays on the hidden enemy while that enemy moves across the view count, so resting the
+// crosshair on a doorway someone walks through doesn't. Anyone can do this by chance now and then,
+// so the time spent tracking hidden enemies is summed over the match and compared with the time hidden
+// enemies were near the crosshair at all. Players are flagged once at the end of the demo.
+
+#[derive(Default)]
+struct PlayerStats {
+    tracker: AimTracker<Hidden>,
+    // Ticks a hidden enemy was within the engagement fov
+    exposure_ticks: u32,
+    tracked_ticks: u32,
+    tracks: Vec<Value>,
+}
+
+#[derive(Default)]
+pub struct Wallhack {
+    params: Parameters,
+
+    cloaks: CloakTracker,
+    stats: HashMap<u64, PlayerStats>,
+}
+
+impl Wallhack {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                // Crosshair within this many degrees of a target (or over its body) is on it
+                ("max_track_angle".to_string(), Parameter::Float(4.0)),
+                ("body_radius".to_string(), Parameter::Float(24.0)),
+                ("engagement_fov".to_string(), Parameter::Float(30.0)),
+                ("min_track_ticks".to_string(), Parameter::Int(33)),
+                // How far the target has to move across the view during a track
+                ("min_bearing_change".to_string(), Parameter::Float(10.0)),
+                ("min_tracks".to_string(), Parameter::Int(5)),
+                ("min_tracked_fraction".to_string(), Parameter::Float(0.2)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn on_target(&self, target: &AimTarget) -> bool {
+        let max_track_angle: f32 = get_parameter_value(&self.params, "max_track_angle");
+        let body_radius: f32 = get_parameter_value(&self.params, "body_radius");
+        on_target(target, max_track_angle, body_radius)
+    }
+
+    // Keep the track if it followed the target long enough
+    fn close_track(
+        &self,
+        stats: &mut PlayerStats,
+        state: &CheatAnalyserState,
+        track: Option<Track<Hidden>>,
+        revealed: bool,
+    ) {
+        let min_track_ticks: i32 = get_parameter_value(&self.params, "min_track_ticks");
+        let min_bearing_change: f32 = get_parameter_value(&self.params, "min_bearing_change");
+
+        let Some(track) = track else {
+            return;
+        };
+        if !track.is_tracking(min_track_ticks.max(0) as u32, min_bearing_change) {
+            return;
+        }
+        stats.tracked_ticks += track.ticks;
+        stats.tracks.push(json!({
+            "tick": track.start,
+            "target": state.get_id64_from_entid(track.target),
+            "hidden": track.info.name(),
+            "ticks": track.ticks,
+            "bearing_change": track.bearing_change(),
+            "mean_error": track.mean_error(),
+            // The crosshair was still on the target when it became visible
+            "revealed_on_crosshair": revealed,
+        }));
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for Wallhack {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "wallhack"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let engagement_fov: f32 = get_parameter_value(&self.params, "engagement_fov");
+        let ticknum = u32::from(state.tick);
+
+        self.cloaks.update(state);
+
+        let mut stats = std::mem::take(&mut self.stats);
+        for player in &state.players {
+            let Some(info) = &player.info else {
+                continue;
+            };
+            if info.steam_id == "BOT" {
+                continue;
+            }
+            let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+            let player_stats = stats.entry(steam_id).or_default();
+            if !player.in_pvs || player.state != PlayerState::Alive {
+                let track = player_stats.tracker.end();
+                self.close_track(player_stats, state, track, false);
+                continue;
+            }
+
+            let targets: Vec<(AimTarget, Option<Hidden>)> = state
+                .get_aim(player)
+                .into_iter()
+                .flat_map(|aim| aim.targets.iter().copied())
+                .filter_map(|t| {
+                    let other = state.get_player_by_entid(t.entity)?;
+                    Some((t, hidden_from(state, &self.cloaks, player, other)))
+                })
+                .collect();
+            if targets
+                .iter()
+                .any(|(t, hidden)| hidden.is_some() && t.angle() <= engagement_fov)
+            {
+                player_stats.exposure_ticks += 1;
+            }
+
+            // Only the enemy closest to the crosshair counts, so following a visible enemy standing
+            // next to a hidden one doesn't
+            let closest = targets
+                .iter()
+                .min_by(|a, b| a.0.angle().total_cmp(&b.0.angle()))
+                .filter(|(t, _)| self.on_target(t));
+            let Some((target, hidden)) = closest else {
+                let track = player_stats.tracker.end();
+                self.close_track(player_stats, state, track, false);
+                continue;
+            };
+            let Some(hidden) = hidden else {
+                let revealed = player_stats
+                    .tracker
+                    .current()
+                    .is_some_and(|track| track.target == target.entity);
+                let track = player_stats.tracker.end();
+                self.close_track(player_stats, state, track, revealed);
+                continue;
+            };
+            let Some(other) = state.get_player_by_entid(target.entity) else {
+                continue;
+            };
+            let bearing = angles_to(player.eye_position(), other.body_position());
+            let ended = player_stats
+                .tracker
+                .follow(target.entity, ticknum, bearing, target.angle(), *hidden);
+            self.close_track(player_stats, state, ended, false);
+        }
+        self.stats = stats;
+
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_tracks: i32 = get_parameter_value(&self.params, "min_tracks");
+        let min_tracked_fraction: f32 = get_parameter_value(&self.params, "min_tracked_fraction");
+
+        let mut detections = Vec::new();
+        for (steam_id, stats) in &self.stats {
+            let fraction = stats.tracked_ticks as f32 / stats.exposure_ticks.max(1) as f32;
+            if (stats.tracks.len() as i32) < min_tracks || fraction < min_tracked_fraction {
+                continue;
+            }
+            let revealed = stats
+                .tracks
+                .iter()
+                .filter(|t| t["revealed_on_crosshair"].as_bool().unwrap_or_default())
+                .count();
+            detections.push(Detection {
+                tick: stats.tracks[0]["tick"].as_u64().unwrap_or_default() as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "tracks": stats.tracks.len(),
+                    "revealed_on_crosshair": revealed,
+                    "tracked_ticks": stats.tracked_ticks,
+                    "exposure_ticks": stats.exposure_ticks,
+                    "tracked_fraction": fraction,
+                    "hidden_tracks": stats.tracks,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::base::cheat_analyser_base::{Building, CheatAnalyserState, Player, PlayerState, Team};
+use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::lib::parameters::{get_parameter_value, Parameter, Parameters};
+use crate::util::helpers::{angle_delta, angles_to, length};
+use crate::util::visibility::{AimTracker, Track};
+
+use anyhow::Error;
+use serde_json::{json, Value};
+use steamid_ng::SteamID;
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::vector::Vector;
+use tf_demo_parser::ParserState;
+
+// Looks for aimbots steering wrangled sentries. The demo doesn't carry the turret's aim (Sentry::angle is
+// only the direction it was built facing), but a wrangled sentry fires at whatever the Engineer's
+// crosshair is on, so the Engineer's view while wrangling is the sentry's aim and is analysed instead:
+// snaps onto an enemy right before the sentry fires, locks that follow an enemy for a long time with
+// almost no error, and near perfect accuracy over all the ticks the sentry fired. Only enemies the
+// sentry could hit count as targets. The sentry fired on every tick its ammo went down.
+// Engineers are flagged once at the end of the demo for repeated snaps or locks. Precise fire alone is
+// easy against a large, slow target up close, so it only backs up snaps or locks too few to flag on
+// their own.
+
+// Height of a sentry's guns above its origin
+const MUZZLE_HEIGHT: f32 = 32.0;
+// Engineer views kept per tick
+const HISTORY_TICKS: usize = 16;
+
+fn is_enemy(team: Team, other: Team) -> bool {
+    matches!(team, Team::Red | Team::Blue) && matches!(other, Team::Red | Team::Blue) && team != other
+}
+
+// Degrees between a view and the closer of the target's head and body centre
+fn aim_error(view: (f32, f32), eye: Vector, target: &Player) -> f32 {
+    angle_delta(view, angles_to(eye, target.eye_position()))
+        .min(angle_delta(view, angles_to(eye, target.body_position())))
+}
+
+#[derive(Default)]
+struct WrangleStats {
+    // (tick, yaw, pitch) while wrangling
+    views: VecDeque<(u32, f32, f32)>,
+    // Ammo of the wrangled sentry as of the previous tick
+    ammo: Option<(EntityId, u16, u16)>,
+    tracker: AimTracker<()>,
+    wrangled_ticks: u32,
+    first_fire_tick: Option<u32>,
+    fire_ticks: u32,
+    precise_fire_ticks: u32,
+    snaps: Vec<Value>,
+    locks: Vec<Value>,
+}
+
+#[derive(Default)]
+pub struct WrangledSentry {
+    params: Parameters,
+
+    stats: HashMap<u64, WrangleStats>,
+}
+
+impl WrangledSentry {
+    pub fn new() -> Self {
+        Self {
+            params: HashMap::from([
+                ("sentry_range".to_string(), Parameter::Float(1100.0)),
+                // Crosshair within this many degrees of an enemy counts as locked on
+                ("max_lock_angle".to_string(), Parameter::Float(1.0)),
+                // View change over the last few ticks before the sentry fires
+                ("snap_ticks".to_string(), Parameter::Int(3)),
+                ("min_snap_angle".to_string(), Parameter::Float(20.0)),
+                ("min_lock_ticks".to_string(), Parameter::Int(33)),
+                // How far the enemy has to move across the view during a lock
+                ("min_bearing_change".to_string(), Parameter::Float(10.0)),
+                ("min_snaps".to_string(), Parameter::Int(3)),
+                ("min_locks".to_string(), Parameter::Int(3)),
+                ("min_fire_ticks".to_string(), Parameter::Int(66)),
+                ("min_precise_fraction".to_string(), Parameter::Float(0.9)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    // Keep the lock if it was long enough and followed a moving enemy
+    fn close_lock(
+        &self,
+        stats: &mut WrangleStats,
+        state: &CheatAnalyserState,
+        lock: Option<Track<()>>,
+    ) {
+        let min_lock_ticks: i32 = get_parameter_value(&self.params, "min_lock_ticks");
+        let min_bearing_change: f32 = get_parameter_value(&self.params, "min_bearing_change");
+
+        let Some(lock) = lock else {
+            return;
+        };
+        if !lock.is_tracking(min_lock_ticks.max(0) as u32, min_bearing_change) {
+            return;
+        }
+        stats.locks.push(json!({
+            "tick": lock.start,
+            "target": state.get_id64_from_entid(lock.target),
+            "ticks": lock.ticks,
+            "bearing_change": lock.bearing_change(),
+            "mean_error": lock.mean_error(),
+        }));
+    }
+}
+
+impl<'a> CheatAlgorithm<'a> for WrangledSentry {
+    fn default(&self) -> bool {
+        false
+    }
+
+    fn algorithm_name(&self) -> &str {
+        "wrangled_sentry"
+    }
+
+    fn on_tick(&mut self, state: &CheatAnalyserState, _: &ParserState) -> Result<Vec<Detection>, Error> {
+        let sentry_range: f32 = get_parameter_value(&self.params, "sentry_range");
+        let max_lock_angle: f32 = get_parameter_value(&self.params, "max_lock_angle");
+        let snap_ticks: i32 = get_parameter_value(&self.params, "snap_ticks");
+        let min_snap_angle: f32 = get_parameter_value(&self.params, "min_snap_angle");
+        let ticknum = u32::from(state.tick);
+
+        let wrangled: HashMap<u64, (&Player, Vector, EntityId, u16, u16, EntityId)> = state
+            .buildings
+            .values()
+            .filter_map(|building| {
+                let Building::Sentry(sentry) = building else {
+                    return None;
+                };
+                if !sentry.player_controlled || sentry.building || sentry.sapped {
+                    return None;
+                }
+                let engineer = state.get_player_by_entid(sentry.builder)?;
+                let info = engineer.info.as_ref()?;
+                if !engineer.in_pvs || engineer.state != PlayerState::Alive || info.steam_id == "BOT" {
+                    return None;
+                }
+                let steam_id = u64::from(SteamID::from_steam3(&info.steam_id).unwrap_or_default());
+                let muzzle = Vector {
+                    z: sentry.position.z + MUZZLE_HEIGHT,
+                    ..sentry.position
+                };
+                let entry = (
+                    engineer,
+                    muzzle,
+                    sentry.entity,
+                    sentry.shells,
+                    sentry.rockets,
+                    sentry.auto_aim_target,
+                );
+                Some((steam_id, entry))
+            })
+            .collect();
+
+        let mut stats = std::mem::take(&mut self.stats);
+        for (steam_id, player_stats) in stats.iter_mut() {
+            if !wrangled.contains_key(steam_id) {
+                let lock = player_stats.tracker.end();
+                self.close_lock(player_stats, state, lock);
+                player_stats.views.clear();
+                player_stats.ammo = None;
+            }
+        }
+
+        for (steam_id, (engineer, muzzle, sentry, shells, rockets, auto_aim_target)) in wrangled {
+            let player_stats = stats.entry(steam_id).or_default();
+            player_stats.wrangled_ticks += 1;
+            let view = (engineer.view_angle, engineer.pitch_angle);
+            player_stats.views.push_back((ticknum, view.0, view.1));
+            if player_stats.views.len() > HISTORY_TICKS {
+                player_stats.views.pop_front();
+            }
+            let fired = matches!(
+                player_stats.ammo,
+                Some((id, s, r)) if id == sentry && (shells < s || rockets < r)
+            );
+            player_stats.ammo = Some((sentry, shells, rockets));
+
+            // Enemies the sentry could hit, by how far the Engineer's crosshair is from them
+            let eye = engineer.eye_position();
+            let target = state
+                .players
+                .iter()
+                .filter(|p| {
+                    p.in_pvs
+                        && p.state == PlayerState::Alive
+                        && is_enemy(engineer.team, p.team)
+                        && length(p.body_position() - muzzle) <= sentry_range
+                        && state
+                            .map
+                            .as_ref()
+                            .is_none_or(|map| map.can_see(muzzle, p.body_position()))
+                })
+                .map(|p| (p, aim_error(view, eye, p)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            if fired {
+                player_stats.fire_ticks += 1;
+                player_stats.first_fire_tick.get_or_insert(ticknum);
+                if let Some((target, error)) = target {
+                    let precise = error <= max_lock_angle;
+                    player_stats.precise_fire_ticks += u32::from(precise);
+                    let before = player_stats.views.iter().rev().nth(snap_ticks.max(1) as usize);
+                    let snap = before.map_or(0.0, |(_, y, p)| angle_delta((*y, *p), view));
+                    if precise && snap >= min_snap_angle {
+                        player_stats.snaps.push(json!({
+                            "tick": ticknum,
+                            "target": state.get_id64_from_entid(target.entity_id()),
+                            "snap_angle": snap,
+                            "error": error,
+                            "auto_aim_target": state.get_id64_from_entid(auto_aim_target),
+                        }));
+                    }
+                }
+            }
+
+            let Some((target, error)) = target.filter(|(_, error)| *error <= max_lock_angle) else {
+                let lock = player_stats.tracker.end();
+                self.close_lock(player_stats, state, lock);
+                continue;
+            };
+            let bearing = angles_to(eye, target.body_position());
+            let lock = player_stats.tracker.follow(target.entity_id(), ticknum, bearing, error, ());
+            self.close_lock(player_stats, state, lock);
+        }
+        self.stats = stats;
+
+        Ok(vec![])
+    }
+
+    fn finish(&mut self) -> Result<Vec<Detection>, Error> {
+        let min_snaps: i32 = get_parameter_value(&self.params, "min_snaps");
+        let min_locks: i32 = get_parameter_value(&self.params, "min_locks");
+        let min_fire_ticks: i32 = get_parameter_value(&self.params, "min_fire_ticks");
+        let min_precise_fraction: f32 = get_parameter_value(&self.params, "min_precise_fraction");
+
+        let mut detections = Vec::new();
+        for (steam_id, stats) in &self.stats {
+            let precise_fraction = stats.precise_fire_ticks as f32 / stats.fire_ticks.max(1) as f32;
+            let snapping = stats.snaps.len() as i32 >= min_snaps;
+            let locking = stats.locks.len() as i32 >= min_locks;
+            let precise =
+                stats.fire_ticks as i32 >= min_fire_ticks && precise_fraction >= min_precise_fraction;
+            let backed_up = precise && !(stats.snaps.is_empty() && stats.locks.is_empty());
+            if !snapping && !locking && !backed_up {
+                continue;
+            }
+            let first_tick = stats
+                .snaps
+                .iter()
+                .chain(&stats.locks)
+                .filter_map(|v| v["tick"].as_u64())
+                .chain(stats.first_fire_tick.map(u64::from))
+                .min()
+                .unwrap_or_default();
+            detections.push(Detection {
+                tick: first_tick as u32,
+                algorithm: self.algorithm_name().to_string(),
+                player: *steam_id,
+                data: json!({
+                    "snapping": snapping,
+                    "locking": locking,
+                    "precise": precise,
+                    "wrangled_ticks": stats.wrangled_ticks,
+                    "fire_ticks": stats.fire_ticks,
+                    "precise_fraction": precise_fraction,
+                    "snaps": stats.snaps,
+                    "locks": stats.locks,
+                }),
+            });
+        }
+        detections.sort_by_key(|d| d.tick);
+        Ok(detections)
+    }
+
+    fn params(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.params)
+    }
+}
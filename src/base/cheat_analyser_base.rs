@@ -10,9 +10,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::str::FromStr;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use steamid_ng::SteamID;
-use tf_demo_parser::demo::data::DemoTick;
+use tf_demo_parser::demo::data::{DemoTick, ServerTick};
 use tf_demo_parser::demo::gameevent_gen::ObjectDestroyedEvent;
 use tf_demo_parser::demo::gamevent::GameEvent;
 use tf_demo_parser::demo::header::Header;
@@ -24,6 +24,7 @@ use tf_demo_parser::demo::packet::message::MessagePacketMeta;
 use tf_demo_parser::demo::packet::stringtable::StringTableEntry;
 use tf_demo_parser::demo::parser::analyser::UserInfo;
 pub use tf_demo_parser::demo::parser::analyser::{Class, Team, UserId};
+pub use tf_demo_parser::demo::data::game_state::PlayerCondition;
 use tf_demo_parser::demo::parser::handler::BorrowMessageHandler;
 use tf_demo_parser::demo::parser::MessageHandler;
 use tf_demo_parser::demo::sendprop::{SendProp, SendPropIdentifier, SendPropValue};
@@ -32,6 +33,9 @@ use tf_demo_parser::{MessageType, ParserState, ReadResult, Stream};
 use web_time::Instant;
 
 use crate::lib::algorithm::{CheatAlgorithm, Detection};
+use crate::util::helpers::{handle_to_entid, simtime_to_tick};
+use crate::util::aim_targets::{estimate_aim_targets, AimEstimate};
+use crate::util::bsp::{load_map, BspMap};
 use crate::dev_print;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
@@ -54,6 +58,11 @@ impl PlayerState {
     }
 }
 
+const TICK_INTERVAL: f32 = 0.015;
+const FL_ONGROUND: u32 = 1 << 0;
+const FL_DUCKING: u32 = 1 << 1;
+const FL_INWATER: u32 = 1 << 9;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct Player {
     entity: EntityId,
@@ -68,15 +77,80 @@ pub struct Player {
     pub info: Option<UserInfo>,
     pub charge: u8,
     pub simtime: u16,
+    // Server tick the player was last simulated at, decoded from simtime
+    pub simulation_tick: u32,
+    // Demo tick of the last packet that updated the player's origin
+    pub origin_update_tick: DemoTick,
+    // Units per second, derived from origin changes between simulation ticks
+    pub velocity: Vector,
+    pub flags: u32,
+    // Number of air jumps (scout double jump) since last touching the ground
+    pub air_dash: u8,
+    // Heads collected with a decapitating sword
+    pub decapitations: u8,
     pub ping: u16,
     pub in_pvs: bool,
+    pub active_weapon: EntityId,
+    conditions: [u8; 20],
     // pub shot_fired: u32,
 }
 
+impl Player {
+    pub fn entity_id(&self) -> EntityId {
+        self.entity
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.flags & FL_ONGROUND != 0
+    }
+
+    pub fn in_water(&self) -> bool {
+        self.flags & FL_INWATER != 0
+    }
+
+    pub fn ducking(&self) -> bool {
+        self.flags & FL_DUCKING != 0
+    }
+
+    // Eye height above the origin, from the class view offsets
+    pub fn view_offset(&self) -> f32 {
+        if self.ducking() {
+            return 45.0;
+        }
+        match self.class {
+            Class::Scout => 65.0,
+            Class::Soldier | Class::Pyro | Class::Demoman | Class::Engineer => 68.0,
+            _ => 75.0,
+        }
+    }
+
+    // Centre of the player's hull
+    pub fn body_position(&self) -> Vector {
+        Vector {
+            z: self.position.z + if self.ducking() { 31.0 } else { 41.0 },
+            ..self.position
+        }
+    }
+
+    pub fn eye_position(&self) -> Vector {
+        Vector {
+            z: self.position.z + self.view_offset(),
+            ..self.position
+        }
+    }
+
+    pub fn has_condition(&self, condition: PlayerCondition) -> bool {
+        let cond = condition as usize;
+        self.conditions
+            .get(cond / 8)
+            .is_some_and(|byte| (byte >> (cond % 8)) & 1 == 1)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct Sentry {
     pub entity: EntityId,
-    pub builder: UserId,
+    pub builder: EntityId,
     pub position: Vector,
     pub level: u8,
     pub max_health: u16,
@@ -84,9 +158,11 @@ pub struct Sentry {
     pub building: bool,
     pub sapped: bool,
     pub team: Team,
+    // Yaw the sentry was built facing. Where the turret is aiming isn't networked.
     pub angle: f32,
     pub player_controlled: bool,
-    pub auto_aim_target: UserId,
+    // Player entity the sentry is aiming at
+    pub auto_aim_target: EntityId,
     pub shells: u16,
     pub rockets: u16,
     pub is_mini: bool,
@@ -95,7 +171,7 @@ pub struct Sentry {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct Dispenser {
     pub entity: EntityId,
-    pub builder: UserId,
+    pub builder: EntityId,
     pub position: Vector,
     pub level: u8,
     pub max_health: u16,
@@ -111,7 +187,7 @@ pub struct Dispenser {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct Teleporter {
     pub entity: EntityId,
-    pub builder: UserId,
+    pub builder: EntityId,
     pub position: Vector,
     pub level: u8,
     pub max_health: u16,
@@ -177,7 +253,7 @@ impl Building {
         }
     }
 
-    pub fn builder(&self) -> UserId {
+    pub fn builder(&self) -> EntityId {
         match self {
             Building::Sentry(Sentry { builder, .. })
             | Building::Dispenser(Dispenser { builder, .. })
@@ -240,6 +316,79 @@ pub enum BuildingClass {
     Teleporter,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Weapon {
+    pub entity: EntityId,
+    pub class_name: String,
+    pub owner: EntityId,
+    pub item_definition_index: u32,
+    // Only set for mediguns
+    pub medigun: Option<Medigun>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct Medigun {
+    pub healing: bool,
+    pub healing_target: EntityId,
+    // Ubercharge (or a Vaccinator bubble) is active
+    pub charge_release: bool,
+    pub charge_level: f32,
+    // Vaccinator resistance: 0 bullet, 1 blast, 2 fire
+    pub resist_type: u8,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum ProjectileType {
+    Rocket,
+    Pipe,
+    Sticky,
+    LooseCannon,
+    Flare,
+    Arrow,
+    HealingBolt,
+    Jar,
+    #[default]
+    Other,
+}
+
+impl ProjectileType {
+    pub fn new(class_name: &str, pipe_type: Option<i64>) -> Self {
+        match (class_name, pipe_type) {
+            ("CTFGrenadePipebombProjectile", Some(1 | 2)) => ProjectileType::Sticky,
+            ("CTFGrenadePipebombProjectile", Some(3)) => ProjectileType::LooseCannon,
+            ("CTFGrenadePipebombProjectile", _) => ProjectileType::Pipe,
+            ("CTFProjectile_Rocket" | "CTFProjectile_SentryRocket" | "CTFProjectile_EnergyBall", _) => {
+                ProjectileType::Rocket
+            }
+            ("CTFProjectile_Flare", _) => ProjectileType::Flare,
+            ("CTFProjectile_Arrow", _) => ProjectileType::Arrow,
+            ("CTFProjectile_HealingBolt", _) => ProjectileType::HealingBolt,
+            ("CTFProjectile_Jar" | "CTFProjectile_JarMilk" | "CTFProjectile_JarGas" | "CTFProjectile_Cleaver", _) => {
+                ProjectileType::Jar
+            }
+            _ => ProjectileType::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Projectile {
+    pub entity: EntityId,
+    pub class_name: String,
+    pub ty: ProjectileType,
+    pub team: Team,
+    pub position: Vector,
+    pub rotation: Vector,
+    pub initial_velocity: Vector,
+    // Weapon entity that fired the projectile
+    pub launcher: EntityId,
+    // Player entity that fired the projectile, or last deflected it
+    pub owner: EntityId,
+    pub deflected: u16,
+    pub critical: bool,
+    pub spawn_tick: DemoTick,
+}
+
 #[derive(Default, Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct World {
     pub boundary_min: Vector,
@@ -273,9 +422,18 @@ pub struct CheatAnalyserState {
     pub entid_to_userid: HashMap<EntityId, UserId>,
     pub userid_to_id64: HashMap<UserId, u64>,
     pub buildings: BTreeMap<EntityId, Building>,
+    pub weapons: HashMap<EntityId, Weapon>,
+    pub projectiles: BTreeMap<EntityId, Projectile>,
     pub world: Option<World>,
+    // Geometry of the demo's map, if a maps folder was set and it contains the map
+    #[serde(skip)]
+    pub map: Option<Arc<BspMap>>,
+    // Who every living player in PVS is aiming at, refreshed every tick before the algorithms run
+    #[serde(skip)]
+    pub aim: HashMap<EntityId, AimEstimate>,
     // pub kills: Vec<Kill>,
     pub tick: DemoTick,
+    pub server_tick: ServerTick,
 }
 
 impl CheatAnalyserState {
@@ -309,6 +467,15 @@ impl CheatAnalyserState {
         self.userid_to_id64.get(&userid).copied()
     }
 
+    pub fn get_id64_from_entid(&self, entid: EntityId) -> Option<u64> {
+        self.get_userid_from_entid(entid)
+            .and_then(|userid| self.get_id64_from_userid(userid))
+    }
+
+    pub fn get_player_by_entid(&self, entid: EntityId) -> Option<&Player> {
+        self.players.iter().find(|player| player.entity == entid)
+    }
+
     pub fn set_entid_to_userid(&mut self, entid: EntityId, userid: UserId) {
         self.entid_to_userid.insert(entid, userid);
     }
@@ -330,6 +497,14 @@ impl CheatAnalyserState {
     pub fn remove_building(&mut self, entity_id: EntityId) {
         self.buildings.remove(&entity_id);
     }
+
+    pub fn get_active_weapon(&self, player: &Player) -> Option<&Weapon> {
+        self.weapons.get(&player.active_weapon)
+    }
+
+    pub fn get_aim(&self, player: &Player) -> Option<&AimEstimate> {
+        self.aim.get(&player.entity_id())
+    }
 }
 
 // ParserState requires a non-self impl of does_handle so I had to create this.
@@ -378,6 +553,14 @@ impl MessageHandler for CheatAnalyser<'_> {
     fn handle_header(&mut self, _header: &tf_demo_parser::demo::header::Header) {
         self.header = Some(_header.clone());
         self.print_metadata();
+        match load_map(&_header.map) {
+            Ok(map) => self.state.map = map,
+            Err(e) => dev_print!(
+                "Couldn't load map {}, line of sight won't be checked: {}",
+                _header.map,
+                e
+            ),
+        }
     }
 
     fn handle_message(&mut self, message: &Message, _tick: DemoTick, parser_state: &ParserState) {
@@ -387,7 +570,9 @@ impl MessageHandler for CheatAnalyser<'_> {
                     self.handle_entity(entity, parser_state);
                 }
             }
-            Message::NetTick(_) => {
+            Message::NetTick(net_tick) => {
+                self.state.server_tick = net_tick.tick;
+                self.state.aim = estimate_aim_targets(&self.state);
                 self.check_progress();
                 for algorithm in &mut self.algorithms {
                     match algorithm.on_tick(&self.state, parser_state) {
@@ -688,7 +873,12 @@ impl<'a> CheatAnalyser<'a> {
             "CObjectSentrygun" => self.handle_sentry_entity(entity, parser_state),
             "CObjectDispenser" => self.handle_dispenser_entity(entity, parser_state),
             "CObjectTeleporter" => self.handle_teleporter_entity(entity, parser_state),
-            _ => {}
+            _ if class_name.starts_with("CTFProjectile_")
+                || class_name == "CTFGrenadePipebombProjectile" =>
+            {
+                self.handle_projectile_entity(entity, parser_state)
+            }
+            _ => self.handle_weapon_entity(entity, parser_state),
         }
     }
 
@@ -741,6 +931,8 @@ impl<'a> CheatAnalyser<'a> {
     }
 
     pub fn handle_player_entity(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
+        let tick = self.state.tick;
+        let server_tick = u32::from(self.state.server_tick);
         let player = self.state.get_or_create_player(entity.entity_index);
 
         const HEALTH_PROP: SendPropIdentifier =
@@ -769,8 +961,29 @@ impl<'a> CheatAnalyser<'a> {
 
         const SIMTIME_PROP: SendPropIdentifier =
             SendPropIdentifier::new("DT_BaseEntity", "m_flSimulationTime");
+        const ACTIVE_WEAPON_PROP: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseCombatCharacter", "m_hActiveWeapon");
+        const PLAYER_COND: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_nPlayerCond");
+        const PLAYER_COND_EX1: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_nPlayerCondEx");
+        const PLAYER_COND_EX2: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_nPlayerCondEx2");
+        const PLAYER_COND_EX3: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_nPlayerCondEx3");
+        const PLAYER_COND_EX4: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_nPlayerCondEx4");
+        const PLAYER_COND_BITS: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerConditionListExclusive", "_condition_bits");
+        const FLAGS_PROP: SendPropIdentifier = SendPropIdentifier::new("DT_BasePlayer", "m_fFlags");
+        const AIR_DASH_PROP: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_iAirDash");
+        const DECAPITATIONS_PROP: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFPlayerShared", "m_iDecapitations");
 
         player.in_pvs = entity.in_pvs;
+        let previous_position = player.position;
+        let previous_simulation_tick = player.simulation_tick;
 
         for prop in entity.props(parser_state) {
             match prop.identifier {
@@ -787,9 +1000,11 @@ impl<'a> CheatAnalyser<'a> {
                     let pos_xy = VectorXY::try_from(&prop.value).unwrap_or_default();
                     player.position.x = pos_xy.x;
                     player.position.y = pos_xy.y;
+                    player.origin_update_tick = tick;
                 }
                 LOCAL_ORIGIN_Z | NON_LOCAL_ORIGIN_Z => {
-                    player.position.z = f32::try_from(&prop.value).unwrap_or_default()
+                    player.position.z = f32::try_from(&prop.value).unwrap_or_default();
+                    player.origin_update_tick = tick;
                 }
                 LOCAL_EYE_ANGLES | NON_LOCAL_EYE_ANGLES => {
                     player.view_angle = f32::try_from(&prop.value).unwrap_or_default()
@@ -798,11 +1013,46 @@ impl<'a> CheatAnalyser<'a> {
                     player.pitch_angle = f32::try_from(&prop.value).unwrap_or_default()
                 }
                 SIMTIME_PROP => {
-                    player.simtime = i64::try_from(&prop.value).unwrap_or_default() as u16
+                    player.simtime = i64::try_from(&prop.value).unwrap_or_default() as u16;
+                    player.simulation_tick =
+                        simtime_to_tick(player.simtime, entity.entity_index, server_tick);
+                }
+                FLAGS_PROP => {
+                    player.flags = i64::try_from(&prop.value).unwrap_or_default() as u32
+                }
+                AIR_DASH_PROP => {
+                    player.air_dash = i64::try_from(&prop.value).unwrap_or_default() as u8
+                }
+                DECAPITATIONS_PROP => {
+                    player.decapitations = i64::try_from(&prop.value).unwrap_or_default() as u8
+                }
+                ACTIVE_WEAPON_PROP => {
+                    player.active_weapon =
+                        handle_to_entid(i64::try_from(&prop.value).unwrap_or_default() as u32)
+                }
+                // Each condition prop holds 32 condition bits
+                PLAYER_COND | PLAYER_COND_BITS | PLAYER_COND_EX1 | PLAYER_COND_EX2
+                | PLAYER_COND_EX3 | PLAYER_COND_EX4 => {
+                    let offset = match prop.identifier {
+                        PLAYER_COND | PLAYER_COND_BITS => 0,
+                        PLAYER_COND_EX1 => 4,
+                        PLAYER_COND_EX2 => 8,
+                        PLAYER_COND_EX3 => 12,
+                        _ => 16,
+                    };
+                    player.conditions[offset..offset + 4].copy_from_slice(
+                        &i64::try_from(&prop.value).unwrap_or_default().to_le_bytes()[0..4],
+                    );
                 }
                 _ => {}
             }
         }
+
+        if previous_simulation_tick > 0 && player.simulation_tick > previous_simulation_tick {
+            let elapsed =
+                (player.simulation_tick - previous_simulation_tick) as f32 * TICK_INTERVAL;
+            player.velocity = (player.position - previous_position) * (1.0 / elapsed);
+        }
     }
 
     pub fn handle_world_entity(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
@@ -826,9 +1076,186 @@ impl<'a> CheatAnalyser<'a> {
         }
     }
 
+    pub fn handle_projectile_entity(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
+        const ROCKET_ORIGIN: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFBaseRocket", "m_vecOrigin");
+        const GRENADE_ORIGIN: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFWeaponBaseGrenadeProj", "m_vecOrigin");
+        const ROCKET_ROTATION: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFBaseRocket", "m_angRotation");
+        const GRENADE_ROTATION: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFWeaponBaseGrenadeProj", "m_angRotation");
+        const ROCKET_VELOCITY: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFBaseRocket", "m_vInitialVelocity");
+        const GRENADE_VELOCITY: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFWeaponBaseGrenadeProj", "m_vInitialVelocity");
+        const TEAM: SendPropIdentifier = SendPropIdentifier::new("DT_BaseEntity", "m_iTeamNum");
+        const OWNER: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseEntity", "m_hOwnerEntity");
+        const THROWER: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseGrenade", "m_hThrower");
+        const ORIGINAL_LAUNCHER: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseProjectile", "m_hOriginalLauncher");
+        const PIPE_TYPE: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFProjectile_Pipebomb", "m_iType");
+        const ROCKET_DEFLECTED: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFBaseRocket", "m_iDeflected");
+        const GRENADE_DEFLECTED: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFWeaponBaseGrenadeProj", "m_iDeflected");
+        const CRITICAL_GRENADE: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFWeaponBaseGrenadeProj", "m_bCritical");
+        const CRITICAL_ROCKET: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFProjectile_Rocket", "m_bCritical");
+        const CRITICAL_FLARE: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFProjectile_Flare", "m_bCritical");
+        const CRITICAL_ARROW: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFProjectile_Arrow", "m_bCritical");
+
+        if entity.update_type == UpdateType::Delete {
+            self.state.projectiles.remove(&entity.entity_index);
+            return;
+        }
+
+        let class_name = self
+            .class_names
+            .get(usize::from(entity.server_class))
+            .map(|class_name| class_name.as_str())
+            .unwrap_or("");
+
+        let tick = self.tick;
+        let projectile = self
+            .state
+            .projectiles
+            .entry(entity.entity_index)
+            .or_insert_with(|| Projectile {
+                entity: entity.entity_index,
+                class_name: class_name.to_string(),
+                ty: ProjectileType::new(class_name, None),
+                spawn_tick: tick,
+                ..Projectile::default()
+            });
+
+        for prop in entity.props(parser_state) {
+            match prop.identifier {
+                ROCKET_ORIGIN | GRENADE_ORIGIN => {
+                    projectile.position = Vector::try_from(&prop.value).unwrap_or_default()
+                }
+                ROCKET_ROTATION | GRENADE_ROTATION => {
+                    projectile.rotation = Vector::try_from(&prop.value).unwrap_or_default()
+                }
+                ROCKET_VELOCITY | GRENADE_VELOCITY => {
+                    projectile.initial_velocity = Vector::try_from(&prop.value).unwrap_or_default()
+                }
+                TEAM => projectile.team = Team::new(i64::try_from(&prop.value).unwrap_or_default()),
+                OWNER | THROWER => {
+                    projectile.owner =
+                        handle_to_entid(i64::try_from(&prop.value).unwrap_or_default() as u32)
+                }
+                ORIGINAL_LAUNCHER => {
+                    projectile.launcher =
+                        handle_to_entid(i64::try_from(&prop.value).unwrap_or_default() as u32)
+                }
+                PIPE_TYPE => {
+                    projectile.ty = ProjectileType::new(
+                        &projectile.class_name,
+                        Some(i64::try_from(&prop.value).unwrap_or_default()),
+                    )
+                }
+                ROCKET_DEFLECTED | GRENADE_DEFLECTED => {
+                    projectile.deflected = i64::try_from(&prop.value).unwrap_or_default() as u16
+                }
+                CRITICAL_GRENADE | CRITICAL_ROCKET | CRITICAL_FLARE | CRITICAL_ARROW => {
+                    projectile.critical = i64::try_from(&prop.value).unwrap_or_default() > 0
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Weapons come in dozens of server classes, so we recognise them by their owner prop instead.
+    pub fn handle_weapon_entity(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
+        const OWNER: SendPropIdentifier =
+            SendPropIdentifier::new("DT_BaseCombatWeapon", "m_hOwner");
+        const ITEM_DEFINITION_INDEX: SendPropIdentifier =
+            SendPropIdentifier::new("DT_ScriptCreatedItem", "m_iItemDefinitionIndex");
+        const MEDIGUN_HEALING: SendPropIdentifier =
+            SendPropIdentifier::new("DT_WeaponMedigun", "m_bHealing");
+        const MEDIGUN_TARGET: SendPropIdentifier =
+            SendPropIdentifier::new("DT_WeaponMedigun", "m_hHealingTarget");
+        const MEDIGUN_CHARGE_RELEASE: SendPropIdentifier =
+            SendPropIdentifier::new("DT_WeaponMedigun", "m_bChargeRelease");
+        const MEDIGUN_RESIST_TYPE: SendPropIdentifier =
+            SendPropIdentifier::new("DT_WeaponMedigun", "m_nChargeResistType");
+        const MEDIGUN_CHARGE_LOCAL: SendPropIdentifier =
+            SendPropIdentifier::new("DT_LocalTFWeaponMedigunData", "m_flChargeLevel");
+        const MEDIGUN_CHARGE_NON_LOCAL: SendPropIdentifier =
+            SendPropIdentifier::new("DT_TFWeaponMedigunDataNonLocal", "m_flChargeLevel");
+
+        if entity.update_type == UpdateType::Delete {
+            self.state.weapons.remove(&entity.entity_index);
+            return;
+        }
+
+        if !self.state.weapons.contains_key(&entity.entity_index) {
+            if entity.update_type != UpdateType::Enter
+                || !entity.props(parser_state).any(|prop| prop.identifier == OWNER)
+            {
+                return;
+            }
+            let class_name = self
+                .class_names
+                .get(usize::from(entity.server_class))
+                .map(|class_name| class_name.to_string())
+                .unwrap_or_default();
+            self.state.weapons.insert(
+                entity.entity_index,
+                Weapon {
+                    entity: entity.entity_index,
+                    class_name,
+                    ..Weapon::default()
+                },
+            );
+        }
+
+        let Some(weapon) = self.state.weapons.get_mut(&entity.entity_index) else {
+            return;
+        };
+        for prop in entity.props(parser_state) {
+            match prop.identifier {
+                OWNER => {
+                    weapon.owner =
+                        handle_to_entid(i64::try_from(&prop.value).unwrap_or_default() as u32)
+                }
+                ITEM_DEFINITION_INDEX => {
+                    weapon.item_definition_index =
+                        i64::try_from(&prop.value).unwrap_or_default() as u32
+                }
+                MEDIGUN_HEALING => {
+                    weapon.medigun.get_or_insert_with(Medigun::default).healing =
+                        i64::try_from(&prop.value).unwrap_or_default() > 0
+                }
+                MEDIGUN_TARGET => {
+                    weapon.medigun.get_or_insert_with(Medigun::default).healing_target =
+                        handle_to_entid(i64::try_from(&prop.value).unwrap_or_default() as u32)
+                }
+                MEDIGUN_CHARGE_RELEASE => {
+                    weapon.medigun.get_or_insert_with(Medigun::default).charge_release =
+                        i64::try_from(&prop.value).unwrap_or_default() > 0
+                }
+                MEDIGUN_RESIST_TYPE => {
+                    weapon.medigun.get_or_insert_with(Medigun::default).resist_type =
+                        i64::try_from(&prop.value).unwrap_or_default() as u8
+                }
+                MEDIGUN_CHARGE_LOCAL | MEDIGUN_CHARGE_NON_LOCAL => {
+                    weapon.medigun.get_or_insert_with(Medigun::default).charge_level =
+                        f32::try_from(&prop.value).unwrap_or_default()
+                }
+                _ => {}
+            }
+        }
+    }
+
     pub fn handle_sentry_entity(&mut self, entity: &PacketEntity, parser_state: &ParserState) {
-        const ANGLE: SendPropIdentifier =
-            SendPropIdentifier::new("DT_TFNonLocalPlayerExclusive", "m_angEyeAngles[1]");
         const MINI: SendPropIdentifier =
             SendPropIdentifier::new("DT_BaseObject", "m_bMiniBuilding");
         const CONTROLLED: SendPropIdentifier =
@@ -854,7 +1281,6 @@ impl<'a> CheatAnalyser<'a> {
         if let Building::Sentry(sentry) = building {
             for prop in entity.props(parser_state) {
                 match prop.identifier {
-                    ANGLE => sentry.angle = f32::try_from(&prop.value).unwrap_or_default(),
                     MINI => sentry.is_mini = i64::try_from(&prop.value).unwrap_or_default() > 0,
                     CONTROLLED => {
                         sentry.player_controlled =
@@ -862,7 +1288,7 @@ impl<'a> CheatAnalyser<'a> {
                     }
                     TARGET => {
                         sentry.auto_aim_target =
-                            UserId::from(i64::try_from(&prop.value).unwrap_or_default() as u16)
+                            handle_to_entid(i64::try_from(&prop.value).unwrap_or_default() as u32)
                     }
                     SHELLS => sentry.shells = i64::try_from(&prop.value).unwrap_or_default() as u16,
                     ROCKETS => {
@@ -1034,13 +1460,14 @@ impl<'a> CheatAnalyser<'a> {
                             *position = Vector::try_from(&prop.value).unwrap_or_default()
                         }
                         TEAM => *team = Team::new(i64::try_from(&prop.value).unwrap_or_default()),
-                        ANGLE => *angle = f32::try_from(&prop.value).unwrap_or_default(),
+                        // (pitch, yaw, roll)
+                        ANGLE => *angle = Vector::try_from(&prop.value).unwrap_or_default().y,
                         SAPPED => *sapped = i64::try_from(&prop.value).unwrap_or_default() > 0,
                         BUILDING => *building = i64::try_from(&prop.value).unwrap_or_default() > 0,
                         LEVEL => *level = i64::try_from(&prop.value).unwrap_or_default() as u8,
                         BUILDER => {
                             *builder =
-                                UserId::from(i64::try_from(&prop.value).unwrap_or_default() as u16)
+                                handle_to_entid(i64::try_from(&prop.value).unwrap_or_default() as u32)
                         }
                         MAX_HEALTH => {
                             *max_health = i64::try_from(&prop.value).unwrap_or_default() as u16
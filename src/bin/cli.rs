@@ -4,6 +4,7 @@ use analysis_template::{
         algorithm::{analyse, get_algorithms, CheatAlgorithm},
         parameters::Parameters,
     },
+    util::{bsp::set_maps_dir, items_game::load_items_game},
     SILENT,
 };
 use std::{
@@ -42,6 +43,18 @@ fn main() -> Result<(), Error> {
         "Parameter json file to use for the algorithms",
         "PATH",
     );
+    opts.optopt(
+        "g",
+        "items-game",
+        "items_game.txt from your TF2 install (tf/scripts/items/) to resolve weapon names",
+        "PATH",
+    );
+    opts.optopt(
+        "m",
+        "maps",
+        "maps folder from your TF2 install (tf/maps/) to check line of sight against map geometry",
+        "PATH",
+    );
 
     fn print_help(opts: &getopts::Options) {
         println!("{}", opts.usage("Usage: analysis-template [options]"));
@@ -104,6 +117,16 @@ fn main() -> Result<(), Error> {
         }
     }
 
+    if let Some(items_game_path) = matches.opt_str("g") {
+        let count = load_items_game(std::path::Path::new(&items_game_path))
+            .expect("Couldn't load items_game.txt");
+        dev_print!("Loaded {} item definitions from {}", count, items_game_path);
+    }
+
+    if let Some(maps_path) = matches.opt_str("m") {
+        set_maps_dir(std::path::Path::new(&maps_path));
+    }
+
     let unknown_algorithms: Vec<String> = specified_algorithms
         .into_iter()
         .filter(|a| algorithms.iter().all(|b| b.algorithm_name() != *a))
@@ -6,6 +6,10 @@ use analysis_template::{
         algorithm::{analyse, get_algorithms, Detection},
         parameters::{Parameter, Parameters},
     },
+    util::{
+        bsp::set_maps_dir,
+        items_game::{items_game_loaded, load_items_game},
+    },
 };
 use eframe::egui;
 use itertools::Itertools;
@@ -61,6 +65,12 @@ impl Gui {
                 }
             }
         }
+        if let Err(e) = load_items_game(std::path::Path::new("items_game.txt")) {
+            println!("No items_game.txt loaded, weapons will only show their ids: {e}");
+        }
+        if std::path::Path::new("maps").is_dir() {
+            set_maps_dir(std::path::Path::new("maps"));
+        }
         let (send, recv) = std::sync::mpsc::channel();
         Self {
             algos: HashMap::from_iter(
@@ -210,6 +220,17 @@ impl eframe::App for Gui {
                         self.file = Some(path);
                     }
                 }
+                if ui.button(if items_game_loaded() { "Items ✔" } else { "Items..." }).clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("items_game.txt", &["txt"])
+                        .pick_file()
+                    {
+                        match load_items_game(&path) {
+                            Ok(count) => println!("Loaded {count} item definitions from {}", path.display()),
+                            Err(e) => println!("Error while loading items_game.txt: {e:#?}"),
+                        }
+                    }
+                }
                 ui.add_enabled_ui(self.file.is_some(), |ui|{
                     if ui.button("Analyse").clicked() {
                         self.analyse();
@@ -280,10 +301,17 @@ impl eframe::App for Gui {
                                 self.selected_player.and_then(|p| self.detections.get(&p))
                             {
                                 for (i, det) in detections.iter().enumerate() {
+                                    let weapon = det
+                                        .data
+                                        .as_object()
+                                        .and_then(|data| data.iter().find(|(k, _)| k.trim_start_matches('\u{200b}') == "weapon"))
+                                        .and_then(|(_, weapon)| weapon.get("name"))
+                                        .and_then(|name| name.as_str())
+                                        .map_or(String::new(), |name| format!(" ({name})"));
                                     if ui
                                         .selectable_label(
                                             self.selected_detection.is_some_and(|si| si == i),
-                                            format!("{}: {}", det.tick, det.algorithm),
+                                            format!("{}: {}{}", det.tick, det.algorithm, weapon),
                                         )
                                         .clicked()
                                     {
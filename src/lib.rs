@@ -9,9 +9,32 @@ pub mod algorithms {
     pub mod viewangles_180degrees;
     pub mod viewangles_to_csv;
     pub mod write_to_file;
+    pub mod aim_lock;
+    pub mod aim_telemetry;
     pub mod angle_history;
+    pub mod anti_aim;
+    pub mod auto_airblast;
+    pub mod auto_backstab;
+    pub mod auto_detonation;
+    pub mod auto_uber;
+    pub mod autostrafe;
     pub mod backtrack;
+    pub mod bhop;
+    pub mod cloaked_spy;
+    pub mod crit_hack;
     pub mod double_tap;
+    pub mod fake_lag;
+    pub mod fire_rate;
+    pub mod out_of_world;
+    pub mod projectile_aim;
+    pub mod silent_aim;
+    pub mod sniper_reaction;
+    pub mod speedhack;
+    pub mod teleport;
+    pub mod tickbase;
+    pub mod triggerbot;
+    pub mod wallhack;
+    pub mod wrangled_sentry;
     pub mod nocrex {
         pub mod aimsnap;
         pub mod angle_repeat;
@@ -20,7 +43,12 @@ pub mod algorithms {
 }
 
 pub mod util {
+    pub mod aim_targets;
+    pub mod bsp;
     pub mod helpers;
+    pub mod items_game;
+    pub mod shots;
+    pub mod visibility;
     pub mod nocrex {
         pub mod jankguard;
     }
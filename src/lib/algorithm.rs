@@ -4,9 +4,32 @@ pub use crate::algorithms::{
     viewangles_180degrees::ViewAngles180Degrees,
     viewangles_to_csv::ViewAnglesToCSV,
     write_to_file::WriteToFile,
+    aim_lock::AimLock,
+    aim_telemetry::AimTelemetry,
     angle_history::AngleHistory,
+    anti_aim::AntiAim,
+    auto_airblast::AutoAirblast,
+    auto_backstab::AutoBackstab,
+    auto_detonation::AutoDetonation,
+    auto_uber::AutoUber,
+    autostrafe::Autostrafe,
     backtrack::BackTrack,
+    bhop::Bhop,
+    cloaked_spy::CloakedSpy,
+    crit_hack::CritHack,
     double_tap::DoubleTap,
+    fake_lag::FakeLag,
+    fire_rate::FireRate,
+    out_of_world::OutOfWorld,
+    projectile_aim::ProjectileAim,
+    silent_aim::SilentAim,
+    sniper_reaction::SniperReaction,
+    speedhack::Speedhack,
+    teleport::UnexplainedTeleport,
+    tickbase::Tickbase,
+    triggerbot::Triggerbot,
+    wallhack::Wallhack,
+    wrangled_sentry::WrangledSentry,
     nocrex:: {
         aimsnap::AimSnap, 
         angle_repeat::AngleRepeat, 
@@ -38,6 +61,29 @@ pub fn get_algorithms() -> Vec<Box<dyn CheatAlgorithm<'static> + Send>> {
         Box::new(AimSnap::new()),
         Box::new(BackTrack::new()),
         Box::new(DoubleTap::new()),
+        Box::new(FireRate::new()),
+        Box::new(Tickbase::new()),
+        Box::new(FakeLag::new()),
+        Box::new(Bhop::new()),
+        Box::new(Autostrafe::new()),
+        Box::new(Speedhack::new()),
+        Box::new(UnexplainedTeleport::new()),
+        Box::new(OutOfWorld::new()),
+        Box::new(SilentAim::new()),
+        Box::new(ProjectileAim::new()),
+        Box::new(AimTelemetry::new()),
+        Box::new(AimLock::new()),
+        Box::new(Triggerbot::new()),
+        Box::new(CritHack::new()),
+        Box::new(AntiAim::new()),
+        Box::new(AutoAirblast::new()),
+        Box::new(AutoDetonation::new()),
+        Box::new(AutoBackstab::new()),
+        Box::new(AutoUber::new()),
+        Box::new(SniperReaction::new()),
+        Box::new(Wallhack::new()),
+        Box::new(CloakedSpy::new()),
+        Box::new(WrangledSentry::new()),
     ]
 }
 
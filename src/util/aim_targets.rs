@@ -0,0 +1,82 @@
+// Estimates who every player is aiming at: the angular distance from their eye ray to each
+// enemy's head and body centre. The analyser runs estimate_aim_targets once per tick and keeps the
+// result in CheatAnalyserState::aim, so algorithms should read it from there.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tf_demo_parser::demo::message::packetentities::EntityId;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, Player, PlayerState, Team};
+use crate::util::helpers::{angle_delta, angles_to, length};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct AimTarget {
+    pub entity: EntityId,
+    // Degrees between the eye ray and the target's head / body centre
+    pub head_angle: f32,
+    pub body_angle: f32,
+    pub distance: f32,
+}
+
+impl AimTarget {
+    pub fn angle(&self) -> f32 {
+        self.head_angle.min(self.body_angle)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AimEstimate {
+    pub targets: Vec<AimTarget>,
+}
+
+impl AimEstimate {
+    pub fn closest(&self) -> Option<&AimTarget> {
+        self.targets
+            .iter()
+            .min_by(|a, b| a.angle().total_cmp(&b.angle()))
+    }
+
+    pub fn get(&self, entity: EntityId) -> Option<&AimTarget> {
+        self.targets.iter().find(|t| t.entity == entity)
+    }
+}
+
+fn is_enemy(player: &Player, other: &Player) -> bool {
+    matches!(other.team, Team::Red | Team::Blue)
+        && matches!(player.team, Team::Red | Team::Blue)
+        && other.team != player.team
+}
+
+// Enemies that can be aimed at. Without map geometry "visible" only means alive and in PVS.
+fn is_visible_target(player: &Player, other: &Player) -> bool {
+    other.in_pvs && other.state == PlayerState::Alive && is_enemy(player, other)
+}
+
+pub fn estimate_aim(state: &CheatAnalyserState, player: &Player) -> AimEstimate {
+    let eye = player.eye_position();
+    let view = (player.view_angle, player.pitch_angle);
+    AimEstimate {
+        targets: state
+            .players
+            .iter()
+            .filter(|other| is_visible_target(player, other))
+            .map(|other| AimTarget {
+                entity: other.entity_id(),
+                head_angle: angle_delta(view, angles_to(eye, other.eye_position())),
+                body_angle: angle_delta(view, angles_to(eye, other.body_position())),
+                distance: length(other.body_position() - eye),
+            })
+            .collect(),
+    }
+}
+
+// Aim estimates for every living player in PVS
+pub fn estimate_aim_targets(state: &CheatAnalyserState) -> HashMap<EntityId, AimEstimate> {
+    state
+        .players
+        .iter()
+        .filter(|p| p.in_pvs && p.state == PlayerState::Alive)
+        .map(|p| (p.entity_id(), estimate_aim(state, p)))
+        .collect()
+}
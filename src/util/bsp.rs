@@ -0,0 +1,336 @@
+// Loader for TF2 .bsp maps (found in tf/maps/ of a TF2 install), used to check whether two points can
+// see each other. Demos only name their map, so the user has to point us at a local maps folder; the
+// map is never downloaded. Only the world's brushes are loaded: displacements (terrain), props and
+// brush entities like doors don't block sight. Lines are traced down the BSP tree, so a query only
+// tests the brushes in the leaves the line passes through.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Error};
+use lazy_static::lazy_static;
+use tf_demo_parser::demo::vector::Vector;
+
+use crate::util::helpers::dot;
+
+lazy_static! {
+    static ref MAPS_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+    // Maps are shared between demos on the same map, e.g. when the GUI analyses several in a row
+    static ref MAPS: RwLock<HashMap<String, Arc<BspMap>>> = RwLock::new(HashMap::new());
+}
+
+const BSP_IDENT: &[u8; 4] = b"VBSP";
+const LUMP_PLANES: usize = 1;
+const LUMP_NODES: usize = 5;
+const LUMP_LEAFS: usize = 10;
+const LUMP_LEAFBRUSHES: usize = 17;
+const LUMP_BRUSHES: usize = 18;
+const LUMP_BRUSHSIDES: usize = 19;
+const LUMP_COUNT: usize = 64;
+
+// Brush contents that can't be seen through (CONTENTS_SOLID | CONTENTS_OPAQUE)
+const CONTENTS_BLOCK_SIGHT: i32 = 0x1 | 0x80;
+
+// Size of the on-disk structs
+const PLANE_SIZE: usize = 20;
+const NODE_SIZE: usize = 32;
+// Version 0 leaves carry an extra ambient lighting cube
+const LEAF_SIZE_V0: usize = 56;
+const LEAF_SIZE_V1: usize = 32;
+const BRUSH_SIZE: usize = 12;
+const BRUSHSIDE_SIZE: usize = 8;
+
+fn read_i32(data: &[u8], offset: usize) -> Result<i32, Error> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or_else(|| anyhow!("Unexpected end of data at {offset}"))?;
+    Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// Offsets, lengths and indices, which a corrupt file could make negative
+fn read_index(data: &[u8], offset: usize) -> Result<usize, Error> {
+    let value = read_i32(data, offset)?;
+    usize::try_from(value).map_err(|_| anyhow!("Negative offset or index {value} at {offset}"))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .ok_or_else(|| anyhow!("Unexpected end of data at {offset}"))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_f32(data: &[u8], offset: usize) -> Result<f32, Error> {
+    Ok(f32::from_bits(read_i32(data, offset)? as u32))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Plane {
+    normal: Vector,
+    dist: f32,
+}
+
+impl Plane {
+    fn distance(&self, point: Vector) -> f32 {
+        dot(self.normal, point) - self.dist
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Node {
+    plane: usize,
+    // Negative children are leaves: -1 - leaf index
+    children: [i32; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Leaf {
+    first_brush: usize,
+    brushes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Brush {
+    // Indices into BspMap::planes, facing out of the brush
+    planes: Vec<usize>,
+}
+
+#[derive(Default, PartialEq)]
+pub struct BspMap {
+    pub name: String,
+    planes: Vec<Plane>,
+    nodes: Vec<Node>,
+    leaves: Vec<Leaf>,
+    leaf_brushes: Vec<usize>,
+    // Only brushes that block sight, the others are None
+    brushes: Vec<Option<Brush>>,
+}
+
+// The geometry is far too large to print
+impl fmt::Debug for BspMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BspMap")
+            .field("name", &self.name)
+            .field("nodes", &self.nodes.len())
+            .field("brushes", &self.brushes.len())
+            .finish()
+    }
+}
+
+impl BspMap {
+    pub fn from_file(path: &Path) -> Result<BspMap, Error> {
+        let data = std::fs::read(path)?;
+        let name = path
+            .file_stem()
+            .map_or(String::new(), |s| s.to_string_lossy().into_owned());
+        BspMap::parse(&name, &data)
+    }
+
+    pub fn parse(name: &str, data: &[u8]) -> Result<BspMap, Error> {
+        if data.get(0..4) != Some(BSP_IDENT.as_slice()) {
+            return Err(anyhow!("Not a Source engine map (missing VBSP header)"));
+        }
+        // Each lump entry is offset, length, version and a four byte id
+        let lump = |index: usize| -> Result<(&[u8], i32), Error> {
+            if index >= LUMP_COUNT {
+                return Err(anyhow!("No lump {index}"));
+            }
+            let entry = 8 + index * 16;
+            let offset = read_index(data, entry)?;
+            let length = read_index(data, entry + 4)?;
+            let version = read_i32(data, entry + 8)?;
+            let bytes = offset
+                .checked_add(length)
+                .and_then(|end| data.get(offset..end))
+                .ok_or_else(|| anyhow!("Lump {index} is out of bounds"))?;
+            if bytes.starts_with(b"LZMA") {
+                return Err(anyhow!("Lump {index} is compressed, which isn't supported"));
+            }
+            Ok((bytes, version))
+        };
+
+        let (plane_data, _) = lump(LUMP_PLANES)?;
+        let planes = plane_data
+            .chunks_exact(PLANE_SIZE)
+            .map(|p| {
+                Ok(Plane {
+                    normal: Vector {
+                        x: read_f32(p, 0)?,
+                        y: read_f32(p, 4)?,
+                        z: read_f32(p, 8)?,
+                    },
+                    dist: read_f32(p, 12)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let (node_data, _) = lump(LUMP_NODES)?;
+        let nodes = node_data
+            .chunks_exact(NODE_SIZE)
+            .map(|n| {
+                Ok(Node {
+                    plane: read_index(n, 0)?,
+                    children: [read_i32(n, 4)?, read_i32(n, 8)?],
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let (leaf_data, leaf_version) = lump(LUMP_LEAFS)?;
+        let leaf_size = if leaf_version == 0 { LEAF_SIZE_V0 } else { LEAF_SIZE_V1 };
+        let leaves = leaf_data
+            .chunks_exact(leaf_size)
+            .map(|l| {
+                Ok(Leaf {
+                    first_brush: read_u16(l, 24)? as usize,
+                    brushes: read_u16(l, 26)? as usize,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let (leaf_brush_data, _) = lump(LUMP_LEAFBRUSHES)?;
+        let leaf_brushes = leaf_brush_data
+            .chunks_exact(2)
+            .map(|b| Ok(read_u16(b, 0)? as usize))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let (side_data, _) = lump(LUMP_BRUSHSIDES)?;
+        let side_planes = side_data
+            .chunks_exact(BRUSHSIDE_SIZE)
+            .map(|s| Ok(read_u16(s, 0)? as usize))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let (brush_data, _) = lump(LUMP_BRUSHES)?;
+        let brushes = brush_data
+            .chunks_exact(BRUSH_SIZE)
+            .map(|b| {
+                let first_side = read_index(b, 0)?;
+                let sides = read_index(b, 4)?;
+                let contents = read_i32(b, 8)?;
+                if contents & CONTENTS_BLOCK_SIGHT == 0 {
+                    return Ok(None);
+                }
+                let planes = first_side
+                    .checked_add(sides)
+                    .and_then(|end| side_planes.get(first_side..end))
+                    .ok_or_else(|| anyhow!("Brush sides are out of bounds"))?;
+                Ok(Some(Brush {
+                    planes: planes.to_vec(),
+                }))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // Check every index once here, so tracing never has to. Child nodes always come after their
+        // parent, anything else would make tracing loop forever.
+        let valid = nodes.iter().enumerate().all(|(index, n)| {
+            n.plane < planes.len()
+                && n.children.iter().all(|c| match usize::try_from(*c) {
+                    Ok(node) => node > index && node < nodes.len(),
+                    Err(_) => ((-1 - *c) as usize) < leaves.len(),
+                })
+        }) && leaves
+            .iter()
+            .all(|l| l.first_brush + l.brushes <= leaf_brushes.len())
+            && leaf_brushes.iter().all(|b| *b < brushes.len())
+            && brushes
+                .iter()
+                .flatten()
+                .all(|b| b.planes.iter().all(|p| *p < planes.len()));
+        if nodes.is_empty() || !valid {
+            return Err(anyhow!("Map geometry is corrupt"));
+        }
+
+        Ok(BspMap {
+            name: name.to_string(),
+            planes,
+            nodes,
+            leaves,
+            leaf_brushes,
+            brushes,
+        })
+    }
+
+    // Whether the line between two points doesn't pass through any solid world brush
+    pub fn can_see(&self, a: Vector, b: Vector) -> bool {
+        !self.trace_node(0, a, b)
+    }
+
+    // Whether the segment hits a brush below this node. Node 0 is the root of the world.
+    #[allow(clippy::indexing_slicing)]
+    fn trace_node(&self, child: i32, a: Vector, b: Vector) -> bool {
+        let Ok(index) = usize::try_from(child) else {
+            return self.trace_leaf((-1 - child) as usize, a, b);
+        };
+        let node = &self.nodes[index];
+        let plane = &self.planes[node.plane];
+        let (da, db) = (plane.distance(a), plane.distance(b));
+        if da >= 0.0 && db >= 0.0 {
+            return self.trace_node(node.children[0], a, b);
+        }
+        if da < 0.0 && db < 0.0 {
+            return self.trace_node(node.children[1], a, b);
+        }
+        // The segment crosses the plane: trace the half on a's side first
+        let t = da / (da - db);
+        let mid = a + (b - a) * t;
+        let near = usize::from(da < 0.0);
+        self.trace_node(node.children[near], a, mid) || self.trace_node(node.children[1 - near], mid, b)
+    }
+
+    #[allow(clippy::indexing_slicing)]
+    fn trace_leaf(&self, index: usize, a: Vector, b: Vector) -> bool {
+        let leaf = &self.leaves[index];
+        self.leaf_brushes[leaf.first_brush..leaf.first_brush + leaf.brushes]
+            .iter()
+            .filter_map(|b| self.brushes[*b].as_ref())
+            .any(|brush| self.intersects(brush, a, b))
+    }
+
+    // Clip the segment against every plane of the convex brush, anything left is inside it
+    #[allow(clippy::indexing_slicing)]
+    fn intersects(&self, brush: &Brush, a: Vector, b: Vector) -> bool {
+        let (mut enter, mut exit) = (0.0f32, 1.0f32);
+        for plane in brush.planes.iter().map(|p| &self.planes[*p]) {
+            let (da, db) = (plane.distance(a), plane.distance(b));
+            if da > 0.0 && db > 0.0 {
+                return false;
+            }
+            if da <= 0.0 && db <= 0.0 {
+                continue;
+            }
+            let t = da / (da - db);
+            if da > 0.0 {
+                enter = enter.max(t);
+            } else {
+                exit = exit.min(t);
+            }
+            if enter > exit {
+                return false;
+            }
+        }
+        !brush.planes.is_empty()
+    }
+}
+
+// Set the folder maps are loaded from, usually tf/maps/ of a TF2 install
+pub fn set_maps_dir(path: &Path) {
+    *MAPS_DIR.write().unwrap() = Some(path.to_path_buf());
+}
+
+pub fn maps_dir() -> Option<PathBuf> {
+    MAPS_DIR.read().unwrap().clone()
+}
+
+// Load <maps dir>/<name>.bsp, or return it if it was loaded before. None if no maps folder was set.
+pub fn load_map(name: &str) -> Result<Option<Arc<BspMap>>, Error> {
+    let Some(dir) = maps_dir() else {
+        return Ok(None);
+    };
+    if let Some(map) = MAPS.read().unwrap().get(name) {
+        return Ok(Some(map.clone()));
+    }
+    let map = Arc::new(BspMap::from_file(&dir.join(format!("{name}.bsp")))?);
+    MAPS.write().unwrap().insert(name.to_string(), map.clone());
+    Ok(Some(map))
+}
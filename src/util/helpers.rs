@@ -2,6 +2,7 @@
 // For more complicated structures, consider making your own file in the /util directory.
 
 use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::vector::Vector;
 
 
 // Compute the difference in viewangles. We have to account for the fact viewangles are in a circle.
@@ -34,13 +35,96 @@ pub fn handle_to_entid(handle: u32) -> EntityId {
     EntityId::from(entid)
 }
 
+// Signed difference from yaw a to yaw b, in [-180, 180)
+pub fn angle_diff(a: f32, b: f32) -> f32 {
+    (b - a + 180.0).rem_euclid(360.0) - 180.0
+}
+
+// TF_WEAPON_KNIFE, as sent in player_hurt's weaponid
+pub const WEAPON_ID_KNIFE: u16 = 7;
+// TF_WEAPON_SENTRY_BULLET, TF_WEAPON_SENTRY_ROCKET and TF_WEAPON_SENTRY_REVENGE
+pub const WEAPON_ID_SENTRY_BULLET: u16 = 54;
+pub const WEAPON_ID_SENTRY_ROCKET: u16 = 55;
+pub const WEAPON_ID_SENTRY_REVENGE: u16 = 69;
+
+// Damage dealt by a sentry gun rather than by the Engineer's weapon
+pub fn is_sentry_damage(weapon_id: u16) -> bool {
+    matches!(weapon_id, WEAPON_ID_SENTRY_BULLET | WEAPON_ID_SENTRY_ROCKET)
+}
+
+// The TF_WEAPON_* id player_hurt's weaponid reports for damage done by a weapon of this server class.
+// The Frontier Justice reports TF_WEAPON_SENTRY_REVENGE.
+pub fn weapon_id(class_name: &str) -> Option<u16> {
+    Some(match class_name {
+        "CTFBat" => 1,
+        "CTFBat_Wood" => 2,
+        "CTFBottle" => 3,
+        "CTFFireAxe" => 4,
+        "CTFClub" => 5,
+        "CTFKnife" => WEAPON_ID_KNIFE,
+        "CTFFists" => 8,
+        "CTFShovel" => 9,
+        "CTFWrench" => 10,
+        "CTFBonesaw" => 11,
+        "CTFShotgun" => 12,
+        "CTFShotgun_Soldier" => 13,
+        "CTFShotgun_HWG" => 14,
+        "CTFShotgun_Pyro" => 15,
+        "CTFScatterGun" => 16,
+        "CTFSniperRifle" => 17,
+        "CTFMinigun" => 18,
+        "CTFSMG" => 19,
+        "CTFSyringeGun" => 20,
+        "CTFRocketLauncher" | "CTFRocketLauncher_AirStrike" => 22,
+        "CTFGrenadeLauncher" => 23,
+        "CTFPipebombLauncher" => 24,
+        "CTFFlameThrower" => 25,
+        "CTFPistol" => 41,
+        "CTFPistol_Scout" => 42,
+        "CTFRevolver" => 43,
+        "CTFFlareGun" => 58,
+        "CTFCompoundBow" => 61,
+        "CTFSword" | "CTFKatana" => 64,
+        "CTFRocketLauncher_DirectHit" => 65,
+        "CTFShotgun_Revenge" => WEAPON_ID_SENTRY_REVENGE,
+        "CTFPistol_ScoutPrimary" => 71,
+        "CTFBat_Fish" => 72,
+        "CTFCrossbow" => 73,
+        "CTFStickBomb" => 74,
+        "CTFPistol_ScoutSecondary" => 75,
+        "CTFSodaPopper" => 76,
+        "CTFSniperRifleDecap" => 77,
+        "CTFRaygun" => 78,
+        "CTFParticleCannon" => 79,
+        "CTFRobotArm" => 80,
+        "CTFDRGPomson" => 81,
+        "CTFBat_Giftwrap" => 82,
+        "CTFFlareGun_Revenge" => 84,
+        "CTFPEPBrawlerBlaster" => 85,
+        "CTFShotgunBuildingRescue" => 90,
+        "CTFCannon" => 91,
+        "CTFSniperRifleClassic" => 99,
+        "CTFChargedSMG" => 103,
+        "CTFBreakableSign" => 104,
+        "CTFSlap" => 106,
+        _ => return None,
+    })
+}
+
+// Backstabs are knife crits dealing 6x the victim's health (or at least 600 damage)
+pub fn is_backstab(damage: u16, is_crit: bool, weapon_id: u16, victim_health: u16) -> bool {
+    is_crit
+        && weapon_id == WEAPON_ID_KNIFE
+        && (damage > 600 || damage as f32 > victim_health as f32 * 5.5)
+}
+
 // Compute the total angular difference between two angles
 pub fn angle_delta((ya1, pa1): (f32, f32), (ya2, pa2): (f32, f32)) -> f32 {
     let vec1 = angles_to_unit_vec(ya1, pa1);
     let vec2 = angles_to_unit_vec(ya2, pa2);
 
     let dot = vec1.0 * vec2.0 + vec1.1 * vec2.1 + vec1.2 * vec2.2;
-    dot.acos().to_degrees()
+    dot.clamp(-1.0, 1.0).acos().to_degrees()
 }
 
 fn angles_to_unit_vec(yaw: f32, pitch: f32) -> (f32, f32, f32) {
@@ -51,4 +135,57 @@ fn angles_to_unit_vec(yaw: f32, pitch: f32) -> (f32, f32, f32) {
         pitch.sin(),
         pitch.cos() * yaw.cos(),
     )
-}
\ No newline at end of file
+}
+// m_flSimulationTime is networked as 8 bits relative to a base tick that only moves every 100 ticks
+// (offset per entity so not everything rolls over at once). Reverse the encoding to get the simulation tick.
+// Source: SendProxy_SimulationTime / RecvProxy_SimulationTime in the Source SDK
+pub fn simtime_to_tick(simtime: u16, entity: EntityId, server_tick: u32) -> u32 {
+    let entity_offset = u32::from(entity) % 32;
+    let base = 100 * (server_tick.saturating_sub(entity_offset) / 100);
+    let mut tick = (base + u32::from(simtime & 0xFF)) as i64;
+    let server_tick = server_tick as i64;
+    while tick < server_tick - 127 {
+        tick += 256;
+    }
+    while tick > server_tick + 127 {
+        tick -= 256;
+    }
+    tick.max(0) as u32
+}
+
+// Forward, right and up vectors for a view angle, following Source's AngleVectors (no roll).
+// Pitch is positive when looking down.
+pub fn angle_vectors(yaw: f32, pitch: f32) -> (Vector, Vector, Vector) {
+    let (sy, cy) = yaw.to_radians().sin_cos();
+    let (sp, cp) = pitch.to_radians().sin_cos();
+    (
+        Vector { x: cp * cy, y: cp * sy, z: -sp },
+        Vector { x: sy, y: -cy, z: 0.0 },
+        Vector { x: sp * cy, y: sp * sy, z: cp },
+    )
+}
+
+pub fn dot(a: Vector, b: Vector) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+pub fn length(a: Vector) -> f32 {
+    dot(a, a).sqrt()
+}
+
+// Angle in degrees between two directions
+pub fn vector_angle(a: Vector, b: Vector) -> f32 {
+    let len = length(a) * length(b);
+    if len <= 0.0 {
+        return 0.0;
+    }
+    (dot(a, b) / len).clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+// View angles (yaw, pitch) looking from one point at another
+pub fn angles_to(from: Vector, to: Vector) -> (f32, f32) {
+    let diff = to - from;
+    let yaw = diff.y.atan2(diff.x).to_degrees();
+    let pitch = (-diff.z).atan2(diff.x.hypot(diff.y)).to_degrees();
+    (yaw, pitch)
+}
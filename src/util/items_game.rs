@@ -0,0 +1,488 @@
+// Loader for TF2's items_game.txt (found in tf/scripts/items/ of a TF2 install).
+// Demos only reference weapons by their item definition index, so this lets algorithms and the GUI
+// put a name on them. The file is never downloaded; the user has to point us at a local copy.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+use anyhow::{anyhow, Error};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+lazy_static! {
+    static ref ITEMS_GAME: RwLock<Option<ItemsGame>> = RwLock::new(None);
+}
+
+// A node in a Valve KeyValues document. Keys may repeat, so blocks keep their entries in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyValues {
+    Value(String),
+    Block(Vec<(String, KeyValues)>),
+}
+
+impl KeyValues {
+    pub fn get(&self, key: &str) -> Option<&KeyValues> {
+        match self {
+            KeyValues::Block(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            KeyValues::Value(_) => None,
+        }
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key) {
+            Some(KeyValues::Value(value)) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, KeyValues)] {
+        match self {
+            KeyValues::Block(entries) => entries.as_slice(),
+            KeyValues::Value(_) => &[],
+        }
+    }
+
+    // Merge `other` on top of `self`. Values in `other` win, blocks are merged recursively.
+    fn merge(&mut self, other: &KeyValues) {
+        let (KeyValues::Block(entries), KeyValues::Block(other_entries)) = (&mut *self, other) else {
+            *self = other.clone();
+            return;
+        };
+        for (key, value) in other_entries {
+            match entries.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+                Some((_, existing)) => existing.merge(value),
+                None => entries.push((key.clone(), value.clone())),
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<KeyValues, Error> {
+        let tokens = tokenize(text)?;
+        let mut pos = 0;
+        let entries = parse_block(&tokens, &mut pos, true)?;
+        Ok(KeyValues::Block(entries))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some(other) => s.push(other),
+                            None => return Err(anyhow!("Unterminated escape in KeyValues string")),
+                        },
+                        Some(other) => s.push(other),
+                        None => return Err(anyhow!("Unterminated KeyValues string")),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            // Platform conditionals like [$WIN32] are ignored; we only care about the PC data anyway.
+            '[' => {
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut s = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, '{' | '}' | '"') {
+                        break;
+                    }
+                    s.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Str(s));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_block(tokens: &[Token], pos: &mut usize, root: bool) -> Result<Vec<(String, KeyValues)>, Error> {
+    let mut entries = Vec::new();
+    loop {
+        let key = match tokens.get(*pos) {
+            Some(Token::Str(key)) => key.clone(),
+            Some(Token::Close) if !root => {
+                *pos += 1;
+                return Ok(entries);
+            }
+            None if root => return Ok(entries),
+            Some(token) => return Err(anyhow!("Unexpected KeyValues token {:?} at {}", token, pos)),
+            None => return Err(anyhow!("Unexpected end of KeyValues document")),
+        };
+        *pos += 1;
+        let value = match tokens.get(*pos) {
+            Some(Token::Str(value)) => {
+                *pos += 1;
+                KeyValues::Value(value.clone())
+            }
+            Some(Token::Open) => {
+                *pos += 1;
+                KeyValues::Block(parse_block(tokens, pos, false)?)
+            }
+            _ => return Err(anyhow!("Missing value for KeyValues key \"{}\"", key)),
+        };
+        entries.push((key, value));
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ItemAttribute {
+    pub name: String,
+    pub attribute_class: String,
+    pub value: f32,
+}
+
+impl ItemAttribute {
+    // Attributes that change how often a weapon can fire.
+    pub fn affects_fire_rate(&self) -> bool {
+        self.attribute_class.starts_with("mult_postfiredelay")
+            || self.attribute_class == "mult_minigun_spinup_time"
+            || self.attribute_class == "rocketjump_attackrate_bonus"
+            || self.attribute_class == "auto_fires_full_clip"
+    }
+
+    // Attributes that only change how the item looks, counts kills or can be traded.
+    pub fn is_cosmetic(&self) -> bool {
+        const COSMETIC: [&str; 16] = [
+            "kill_eater",
+            "set_item_tint",
+            "paintkit",
+            "set_attached_particle",
+            "attach_particle",
+            "is_festiv",
+            "item_style",
+            "style_",
+            "cannot_trade",
+            "never_craftable",
+            "cannot_giftwrap",
+            "limited_quantity",
+            "is_australium",
+            "loot_rarity",
+            "custom_name",
+            "custom_desc",
+        ];
+        COSMETIC.iter().any(|c| self.attribute_class.starts_with(c))
+    }
+
+    // Attributes that change the wearer's movement speed.
+    pub fn affects_move_speed(&self) -> bool {
+        self.attribute_class.starts_with("mult_player_movespeed")
+            || self.attribute_class.starts_with("move_speed_bonus")
+            || self.attribute_class.contains("speed_boost")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ItemDefinition {
+    pub index: u32,
+    pub name: String,
+    pub item_class: String,
+    pub item_slot: String,
+    pub used_by_classes: Vec<String>,
+    pub prefab: String,
+    pub attributes: Vec<ItemAttribute>,
+    // Lowest item index that behaves identically to this one (same class, slot, users and gameplay
+    // attributes).
+    pub variant_of: Option<u32>,
+    // What kind of variant this is ("strange", "festive", "botkiller", "skin", "reskin"), if any.
+    pub variant: Option<String>,
+}
+
+impl ItemDefinition {
+    pub fn fire_rate_attributes(&self) -> impl Iterator<Item = &ItemAttribute> {
+        self.attributes.iter().filter(|a| a.affects_fire_rate())
+    }
+
+    // Multiplier applied to the weapon's base refire interval. Health dependent bonuses are ignored.
+    pub fn fire_rate_multiplier(&self) -> f32 {
+        self.attributes
+            .iter()
+            .filter(|a| a.attribute_class == "mult_postfiredelay")
+            .map(|a| a.value)
+            .product()
+    }
+
+    // Multiplier applied to the weapon's random crit chance: 1 unless an attribute changes it, 0 if an
+    // attribute disables random crits.
+    pub fn random_crit_multiplier(&self) -> f32 {
+        self.attributes
+            .iter()
+            .filter(|a| a.attribute_class == "mult_crit_chance")
+            .map(|a| a.value)
+            .product()
+    }
+
+    // Multiplier applied to the damage of one attack, from damage and bullets per shot attributes
+    pub fn damage_multiplier(&self) -> f32 {
+        self.attributes
+            .iter()
+            .filter(|a| matches!(a.attribute_class.as_str(), "mult_dmg" | "mult_bullets_per_shot"))
+            .map(|a| a.value)
+            .product()
+    }
+
+    // Whether the weapon can crit (or mini-crit) on its own terms: while airborne, from behind,
+    // against burning or wet players, with revenge crits, ...
+    pub fn has_conditional_crits(&self) -> bool {
+        self.attributes.iter().any(|a| {
+            let class = a.attribute_class.as_str();
+            (class.contains("crit") && !matches!(class, "mult_crit_chance" | "crit_kill_will_gib"))
+                || class.contains("revenge")
+        })
+    }
+
+    // Fastest movement speed multiplier the item can give, None if it depends on in-game state
+    // (hype, heads, health, ...).
+    pub fn move_speed_multiplier(&self) -> Option<f32> {
+        self.attributes
+            .iter()
+            .filter(|a| a.affects_move_speed())
+            .map(|a| match a.attribute_class.as_str() {
+                "mult_player_movespeed"
+                | "mult_player_movespeed_active"
+                | "mult_player_movespeed_shieldrequired" => Some(a.value.max(1.0)),
+                _ => None,
+            })
+            .product()
+    }
+
+    fn variant_kind(&self) -> &'static str {
+        if self.name.starts_with("Upgradeable ") {
+            "strange"
+        } else if self.name.starts_with("Festive") {
+            "festive"
+        } else if self.name.contains("Botkiller") {
+            "botkiller"
+        } else if self.prefab.split_whitespace().any(|p| p.starts_with("paintkit")) {
+            "skin"
+        } else {
+            "reskin"
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.index,
+            "name": self.name,
+            "item_class": self.item_class,
+            "variant": self.variant,
+            "variant_of": self.variant_of,
+            "fire_rate_attributes": self.fire_rate_attributes().collect::<Vec<_>>(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ItemsGame {
+    pub items: HashMap<u32, ItemDefinition>,
+}
+
+impl ItemsGame {
+    pub fn from_file(path: &Path) -> Result<ItemsGame, Error> {
+        let text = std::fs::read_to_string(path)?;
+        ItemsGame::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<ItemsGame, Error> {
+        let document = KeyValues::parse(text)?;
+        let root = document
+            .get("items_game")
+            .ok_or_else(|| anyhow!("Not an items_game.txt file (missing \"items_game\" root)"))?;
+
+        let empty = KeyValues::Block(vec![]);
+        let prefabs = root.get("prefabs").unwrap_or(&empty);
+
+        // static_attrs only name the attribute, so we need the global table to find its class.
+        let attribute_classes: HashMap<String, String> = root
+            .get("attributes")
+            .unwrap_or(&empty)
+            .entries()
+            .iter()
+            .filter_map(|(_, attr)| {
+                Some((
+                    attr.get_str("name")?.to_lowercase(),
+                    attr.get_str("attribute_class")?.to_string(),
+                ))
+            })
+            .collect();
+
+        let mut items = HashMap::new();
+        for (key, raw_item) in root.get("items").unwrap_or(&empty).entries() {
+            let Ok(index) = key.parse::<u32>() else {
+                continue;
+            };
+            let item = resolve_prefabs(raw_item, prefabs, 0);
+
+            let mut attributes = Vec::new();
+            for (name, attr) in item.get("attributes").unwrap_or(&empty).entries() {
+                attributes.push(ItemAttribute {
+                    name: name.clone(),
+                    attribute_class: attr.get_str("attribute_class").unwrap_or_default().to_string(),
+                    value: attr.get_str("value").and_then(|v| v.parse().ok()).unwrap_or_default(),
+                });
+            }
+            for (name, value) in item.get("static_attrs").unwrap_or(&empty).entries() {
+                let KeyValues::Value(value) = value else {
+                    continue;
+                };
+                attributes.push(ItemAttribute {
+                    name: name.clone(),
+                    attribute_class: attribute_classes
+                        .get(&name.to_lowercase())
+                        .cloned()
+                        .unwrap_or_default(),
+                    value: value.parse().unwrap_or_default(),
+                });
+            }
+
+            let mut used_by_classes: Vec<String> = item
+                .get("used_by_classes")
+                .unwrap_or(&empty)
+                .entries()
+                .iter()
+                .map(|(class, _)| class.to_lowercase())
+                .collect();
+            used_by_classes.sort();
+
+            items.insert(
+                index,
+                ItemDefinition {
+                    index,
+                    name: item.get_str("name").unwrap_or_default().to_string(),
+                    item_class: item.get_str("item_class").unwrap_or_default().to_string(),
+                    item_slot: item.get_str("item_slot").unwrap_or_default().to_string(),
+                    used_by_classes,
+                    prefab: raw_item.get_str("prefab").unwrap_or_default().to_string(),
+                    attributes,
+                    variant_of: None,
+                    variant: None,
+                },
+            );
+        }
+
+        // Items that share class, slot, users and every gameplay attribute are treated as variants
+        // of the one with the lowest index (usually the stock weapon). Comparing only some attributes
+        // would make e.g. the Black Box a variant of the Rocket Launcher.
+        let variant_key = |item: &ItemDefinition| {
+            let mut gameplay: Vec<String> = item
+                .attributes
+                .iter()
+                .filter(|a| !a.is_cosmetic())
+                .map(|a| match a.attribute_class.as_str() {
+                    "" => format!("{}={}", a.name.to_lowercase(), a.value),
+                    class => format!("{}={}", class, a.value),
+                })
+                .collect();
+            gameplay.sort();
+            gameplay.dedup();
+            format!(
+                "{}|{}|{}|{}",
+                item.item_class,
+                item.item_slot,
+                item.used_by_classes.join(","),
+                gameplay.join(",")
+            )
+        };
+        let mut base_items: HashMap<String, u32> = HashMap::new();
+        for item in items.values().filter(|i| !i.item_class.is_empty()) {
+            let base = base_items.entry(variant_key(item)).or_insert(item.index);
+            *base = (*base).min(item.index);
+        }
+        for item in items.values_mut() {
+            if let Some(&base) = base_items.get(&variant_key(item)) {
+                if base != item.index {
+                    item.variant_of = Some(base);
+                    item.variant = Some(item.variant_kind().to_string());
+                }
+            }
+        }
+
+        Ok(ItemsGame { items })
+    }
+
+    pub fn get(&self, index: u32) -> Option<&ItemDefinition> {
+        self.items.get(&index)
+    }
+}
+
+fn resolve_prefabs(item: &KeyValues, prefabs: &KeyValues, depth: usize) -> KeyValues {
+    let Some(prefab_names) = item.get_str("prefab") else {
+        return item.clone();
+    };
+    // Guard against prefabs that (indirectly) reference themselves.
+    if depth > 16 {
+        return item.clone();
+    }
+    let mut resolved = KeyValues::Block(vec![]);
+    for name in prefab_names.split_whitespace() {
+        if let Some(prefab) = prefabs.get(name) {
+            resolved.merge(&resolve_prefabs(prefab, prefabs, depth + 1));
+        }
+    }
+    resolved.merge(item);
+    resolved
+}
+
+// Load items_game.txt and make it available to all algorithms through get_item().
+pub fn load_items_game(path: &Path) -> Result<usize, Error> {
+    let items = ItemsGame::from_file(path)?;
+    let count = items.items.len();
+    *ITEMS_GAME.write().unwrap() = Some(items);
+    Ok(count)
+}
+
+pub fn items_game_loaded() -> bool {
+    ITEMS_GAME.read().unwrap().is_some()
+}
+
+pub fn get_item(index: u32) -> Option<ItemDefinition> {
+    ITEMS_GAME
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|items| items.get(index).cloned())
+}
+
+// Description of an item for detection data. Only contains the index if no items_game.txt was loaded.
+pub fn item_json(index: u32) -> Value {
+    match get_item(index) {
+        Some(item) => item.to_json(),
+        None => json!({ "id": index }),
+    }
+}
@@ -3,10 +3,12 @@
 use std::collections::HashMap;
 
 use crate::base::cheat_analyser_base::{CheatAnalyserState, Player, PlayerState};
+use crate::util::shots::shots_fired;
 use steamid_ng::SteamID;
-use tf_demo_parser::demo::sendprop::SendPropIdentifier;
 
-const TELEPORT_DIST: f32 = 256.0;
+pub const TELEPORT_DIST: f32 = 256.0;
+// A teleport is a single jump, not part of moving that fast every tick (speedhack)
+pub const TELEPORT_STEP_RATIO: f32 = 4.0;
 
 #[derive(Default)]
 struct PlayerData {
@@ -15,6 +17,7 @@ struct PlayerData {
     pub last_fire: u32,
 
     pub prev_state: Option<Player>,
+    pub prev_step: f32,
 }
 
 #[derive(Default)]
@@ -73,31 +76,15 @@ impl JankGuard {
                 }
                 _ => (),
             },
-            // Try to find firing events through tracers and player animations, simplified from megascatterbomb's snippet
+            // Try to find firing events through tracers and player animations
             tf_demo_parser::demo::message::Message::TempEntities(msg) => {
-                for event in &msg.events {
-                    let class = &parser_state.server_classes[usize::from(event.class_id)].name;
-                    if matches!(class.as_str(), "CTEFireBullets" | "CTEPlayerAnimEvent") {
-                        const BULLETS_PLAYER: SendPropIdentifier =
-                            SendPropIdentifier::new("DT_TEFireBullets", "m_iPlayer");
-                        const ANIM_PLAYER: SendPropIdentifier =
-                            SendPropIdentifier::new("DT_TEPlayerAnimEvent", "m_hPlayer");
-
-                        if let Some(prop) = event
-                            .props
-                            .iter()
-                            .find(|p| matches!(p.identifier, BULLETS_PLAYER | ANIM_PLAYER))
-                        {
-                            if let Some(id64) = i64::try_from(&prop.value)
-                                .ok()
-                                .and_then(|id| id.try_into().ok())
-                                .map(|id|crate::util::helpers::handle_to_entid(id))
-                                .and_then(|id| state.entid_to_userid.get(&id))
-                                .and_then(|uid| state.userid_to_id64.get(uid))
-                            {
-                                self.player_data.entry(*id64).or_default().last_fire = tick.into();
-                            }
-                        }
+                for shot in shots_fired(msg, parser_state) {
+                    if let Some(id64) = state
+                        .entid_to_userid
+                        .get(&shot.entity)
+                        .and_then(|uid| state.userid_to_id64.get(uid))
+                    {
+                        self.player_data.entry(*id64).or_default().last_fire = tick.into();
                     }
                 }
             }
@@ -120,16 +107,18 @@ impl JankGuard {
             let steam_id: u64 = u64::from(SteamID::from_steam3(&info.steam_id).unwrap());
 
             let player_data = self.player_data.entry(steam_id).or_default();
-            let prev_player = player_data.prev_state.as_ref();
-
-            if prev_player.as_ref().is_some_and(|p| {
-                // Ignore players that just moved more than 256 HUs in a single tick (teleport)
+            let step = player_data.prev_state.as_ref().map(|p| {
                 let diff = p.position - player.position;
-                let sq_len = diff.x.powi(2) + diff.y.powi(2) + diff.z.powi(2);
-                sq_len > TELEPORT_DIST.powi(2)
+                (diff.x.powi(2) + diff.y.powi(2) + diff.z.powi(2)).sqrt()
+            });
+
+            // Ignore players that just moved more than 256 HUs in a single tick (teleport)
+            if step.is_some_and(|step| {
+                step > TELEPORT_DIST && step > player_data.prev_step * TELEPORT_STEP_RATIO
             }) {
                 player_data.last_teleport = state.tick.into();
             }
+            player_data.prev_step = step.unwrap_or_default();
             states.insert(steam_id, player.clone());
         }
         for (steam_id, player_data) in self.player_data.iter_mut() {
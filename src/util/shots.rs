@@ -0,0 +1,90 @@
+// Reconstructs who fired from the temp entities the server sends for shots: CTEFireBullets for hitscan
+// weapons (with the angles the shot was fired at) and CTEPlayerAnimEvent for every weapon animation.
+// Simplified from megascatterbomb's snippet, originally part of JankGuard.
+
+use tf_demo_parser::demo::message::packetentities::EntityId;
+use tf_demo_parser::demo::message::tempentities::TempEntitiesMessage;
+use tf_demo_parser::demo::sendprop::SendPropIdentifier;
+use tf_demo_parser::ParserState;
+
+use crate::util::helpers::handle_to_entid;
+
+// PlayerAnimEvent_t values up to PLAYERANIMEVENT_ATTACK_GRENADE are attacks
+const ANIM_EVENT_LAST_ATTACK: i64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShotSource {
+    Bullets { yaw: f32, pitch: f32 },
+    Animation { event: i64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShotFired {
+    pub entity: EntityId,
+    pub source: ShotSource,
+}
+
+impl ShotFired {
+    // Whether this is an actual attack, and not a reload, jump or other animation
+    pub fn is_attack(&self) -> bool {
+        match self.source {
+            ShotSource::Bullets { .. } => true,
+            ShotSource::Animation { event } => (0..=ANIM_EVENT_LAST_ATTACK).contains(&event),
+        }
+    }
+}
+
+pub fn shots_fired(msg: &TempEntitiesMessage, parser_state: &ParserState) -> Vec<ShotFired> {
+    const BULLETS_PLAYER: SendPropIdentifier =
+        SendPropIdentifier::new("DT_TEFireBullets", "m_iPlayer");
+    const BULLETS_PITCH: SendPropIdentifier =
+        SendPropIdentifier::new("DT_TEFireBullets", "m_vecAngles[0]");
+    const BULLETS_YAW: SendPropIdentifier =
+        SendPropIdentifier::new("DT_TEFireBullets", "m_vecAngles[1]");
+    const ANIM_PLAYER: SendPropIdentifier =
+        SendPropIdentifier::new("DT_TEPlayerAnimEvent", "m_hPlayer");
+    const ANIM_EVENT: SendPropIdentifier =
+        SendPropIdentifier::new("DT_TEPlayerAnimEvent", "m_iEvent");
+
+    let mut shots = Vec::new();
+    for event in &msg.events {
+        let class = &parser_state.server_classes[usize::from(event.class_id)].name;
+        let prop = |identifier| {
+            event
+                .props
+                .iter()
+                .find(|p| p.identifier == identifier)
+                .map(|p| &p.value)
+        };
+        let int = |identifier| prop(identifier).and_then(|v| i64::try_from(v).ok());
+        let float = |identifier| prop(identifier).and_then(|v| f32::try_from(v).ok());
+
+        match class.as_str() {
+            "CTEFireBullets" => {
+                // m_iPlayer is the player's entity index minus one
+                let (Some(index), Some(yaw), Some(pitch)) =
+                    (int(BULLETS_PLAYER), float(BULLETS_YAW), float(BULLETS_PITCH))
+                else {
+                    continue;
+                };
+                shots.push(ShotFired {
+                    entity: EntityId::from(index as u32 + 1),
+                    source: ShotSource::Bullets { yaw, pitch },
+                });
+            }
+            "CTEPlayerAnimEvent" => {
+                let Some(handle) = int(ANIM_PLAYER) else {
+                    continue;
+                };
+                shots.push(ShotFired {
+                    entity: handle_to_entid(handle as u32),
+                    source: ShotSource::Animation {
+                        event: int(ANIM_EVENT).unwrap_or(-1),
+                    },
+                });
+            }
+            _ => (),
+        }
+    }
+    shots
+}
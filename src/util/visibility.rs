@@ -0,0 +1,195 @@
+// Whether one player can see another. Walls only hide a target if the demo's map was loaded (see
+// util::bsp), otherwise a target only counts as hidden when it is fully cloaked. Players outside PVS
+// aren't networked and their positions are stale, so they can't be checked at all.
+// Call CloakTracker::update from on_tick to know who is fully cloaked. AimTracker follows the target a
+// player's crosshair stays on, to compare following hidden enemies with following visible ones.
+
+use std::collections::HashMap;
+
+use tf_demo_parser::demo::message::packetentities::EntityId;
+
+use crate::base::cheat_analyser_base::{CheatAnalyserState, Player, PlayerCondition, PlayerState};
+use crate::util::aim_targets::AimTarget;
+use crate::util::helpers::angle_delta;
+
+// A Spy fades out over a second after cloaking (tf_spy_invis_time)
+const CLOAK_FADE_TICKS: u32 = 67;
+
+// Cloaked and not flickering from being bumped or shot. Jarate, Mad Milk, gas, fire and bleeding all
+// show on a cloaked Spy, so a Spy under any of them can be seen too.
+pub fn is_cloaked(player: &Player) -> bool {
+    use PlayerCondition::*;
+    player.has_condition(Stealthed)
+        && ![
+            StealthedBlink,
+            Urine,
+            MadMilk,
+            GAS,
+            Burning,
+            BurningPyro,
+            Bleeding,
+            GrapplingHookBleeding,
+        ]
+        .into_iter()
+        .any(|cond| player.has_condition(cond))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CloakTracker {
+    // Tick every cloaked player started cloaking
+    cloaked_since: HashMap<EntityId, u32>,
+    tick: u32,
+}
+
+impl CloakTracker {
+    pub fn update(&mut self, state: &CheatAnalyserState) {
+        self.tick = u32::from(state.tick);
+        let cloaked: Vec<EntityId> = state
+            .players
+            .iter()
+            .filter(|p| p.state == PlayerState::Alive && is_cloaked(p))
+            .map(|p| p.entity_id())
+            .collect();
+        self.cloaked_since.retain(|id, _| cloaked.contains(id));
+        for id in cloaked {
+            self.cloaked_since.entry(id).or_insert(self.tick);
+        }
+    }
+
+    // Ticks since the player started cloaking, or since they last flickered
+    pub fn cloaked_ticks(&self, player: &Player) -> Option<u32> {
+        self.cloaked_since
+            .get(&player.entity_id())
+            .map(|since| self.tick.saturating_sub(*since))
+    }
+
+    // Cloaked long enough to be invisible
+    pub fn fully_cloaked(&self, player: &Player) -> bool {
+        self.cloaked_ticks(player)
+            .is_some_and(|ticks| ticks >= CLOAK_FADE_TICKS)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hidden {
+    Cloaked,
+    Occluded,
+}
+
+impl Hidden {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hidden::Cloaked => "cloaked",
+            Hidden::Occluded => "occluded",
+        }
+    }
+}
+
+// Whether any part of `target` can be seen from `viewer`'s eyes. Always true without map geometry.
+pub fn line_of_sight(state: &CheatAnalyserState, viewer: &Player, target: &Player) -> bool {
+    let Some(map) = &state.map else {
+        return true;
+    };
+    let eye = viewer.eye_position();
+    map.can_see(eye, target.eye_position()) || map.can_see(eye, target.body_position())
+}
+
+// Why `viewer` can't see `target`, or None if it can
+pub fn hidden_from(
+    state: &CheatAnalyserState,
+    cloaks: &CloakTracker,
+    viewer: &Player,
+    target: &Player,
+) -> Option<Hidden> {
+    if cloaks.fully_cloaked(target) {
+        return Some(Hidden::Cloaked);
+    }
+    if !line_of_sight(state, viewer, target) {
+        return Some(Hidden::Occluded);
+    }
+    None
+}
+
+// Crosshair within `max_angle` degrees of the target, or over a body of `body_radius` units
+pub fn on_target(target: &AimTarget, max_angle: f32, body_radius: f32) -> bool {
+    let hitbox = body_radius.atan2(target.distance.max(1.0)).to_degrees();
+    target.angle() <= max_angle.max(hitbox)
+}
+
+// Ticks the crosshair stayed on one target. `info` is whatever the algorithm noted about the target
+// when the track started.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    pub target: EntityId,
+    pub info: T,
+    pub start: u32,
+    pub ticks: u32,
+    // Direction from the player's eyes to the target when the track started, and now
+    pub bearing_start: (f32, f32),
+    pub bearing_last: (f32, f32),
+    pub error_sum: f32,
+}
+
+impl<T> Track<T> {
+    // How far the target moved across the view during the track
+    pub fn bearing_change(&self) -> f32 {
+        angle_delta(self.bearing_start, self.bearing_last)
+    }
+
+    pub fn mean_error(&self) -> f32 {
+        self.error_sum / self.ticks.max(1) as f32
+    }
+
+    // Long enough, and following a moving target rather than resting on a spot it walked through
+    pub fn is_tracking(&self, min_ticks: u32, min_bearing_change: f32) -> bool {
+        self.ticks >= min_ticks && self.bearing_change() >= min_bearing_change
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AimTracker<T> {
+    track: Option<Track<T>>,
+}
+
+impl<T> Default for AimTracker<T> {
+    fn default() -> Self {
+        Self { track: None }
+    }
+}
+
+impl<T> AimTracker<T> {
+    pub fn current(&self) -> Option<&Track<T>> {
+        self.track.as_ref()
+    }
+
+    // The crosshair is on `target` this tick. Returns the previous track if it was on someone else.
+    pub fn follow(
+        &mut self,
+        target: EntityId,
+        tick: u32,
+        bearing: (f32, f32),
+        error: f32,
+        info: T,
+    ) -> Option<Track<T>> {
+        if let Some(track) = self.track.as_mut().filter(|t| t.target == target) {
+            track.ticks += 1;
+            track.bearing_last = bearing;
+            track.error_sum += error;
+            return None;
+        }
+        self.track.replace(Track {
+            target,
+            info,
+            start: tick,
+            ticks: 1,
+            bearing_start: bearing,
+            bearing_last: bearing,
+            error_sum: error,
+        })
+    }
+
+    // The crosshair left its target
+    pub fn end(&mut self) -> Option<Track<T>> {
+        self.track.take()
+    }
+}